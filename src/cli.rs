@@ -0,0 +1,551 @@
+// `schoolterm grades|assignments|attendance|whoami` print their records and exit, for cron jobs,
+// shell prompts, status bars and spreadsheets. they use the login the tui saved, so log in there
+// once first. the passphrase comes from SCHOOLTERM_PASSPHRASE, or gets asked for on the terminal.
+//
+// --format picks table (the default), json, csv or tsv. the table only has the columns worth
+// reading, the others get every field. the field names in the records below are what scripts
+// see, so don't rename them
+use chrono::NaiveDate;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+use crate::schooltool::{encode_password, Assignment, Course, MarkingPeriod, SchoolTool, Student};
+use crate::secrets::{self, SecretStore};
+use crate::{datestr_date, graded_courses, Config, UserData};
+
+pub const COMMANDS: [&str; 4] = ["grades", "assignments", "attendance", "whoami"];
+
+#[derive(Default, Clone, Copy)]
+enum Format {
+    #[default]
+    Table,
+    Json,
+    Csv,
+    Tsv,
+}
+
+#[derive(Default)]
+struct Options {
+    class: Option<String>,
+    quarter: Option<String>,
+    student: Option<String>,
+    building: Option<String>,
+    format: Format,
+}
+fn parse_options(command: &str, args: &[String]) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .cloned()
+                .ok_or_else(|| format!("{} needs a value", arg))
+        };
+        match arg.as_str() {
+            "--class" if command == "assignments" => options.class = Some(value()?),
+            "--quarter" if command != "whoami" => options.quarter = Some(value()?),
+            "--student" if command != "whoami" => options.student = Some(value()?),
+            "--building" if command != "whoami" => options.building = Some(value()?),
+            "--format" => {
+                options.format = match value()?.as_str() {
+                    "table" => Format::Table,
+                    "json" => Format::Json,
+                    "csv" => Format::Csv,
+                    "tsv" => Format::Tsv,
+                    f => {
+                        return Err(format!(
+                            "unknown format {}, it can be table, json, csv or tsv",
+                            f
+                        ))
+                    }
+                }
+            }
+            _ => return Err(format!("unknown argument to {}: {}", command, arg)),
+        }
+    }
+    Ok(options)
+}
+
+trait Record: Serialize + Default {
+    const HEADERS: &'static [&'static str];
+    /// the short version for --format table
+    fn row(&self) -> Vec<String>;
+}
+
+#[derive(Default, Serialize)]
+struct WhoamiRecord {
+    profile: String,
+    username: String,
+    base_url: String,
+    student: String,
+    student_guid: String,
+    building: Option<String>,
+    building_id: Option<i32>,
+    cycle_day: Option<String>,
+}
+impl Record for WhoamiRecord {
+    const HEADERS: &'static [&'static str] = &["Username", "Student", "Building", "Cycle day"];
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.username.clone(),
+            self.student.clone(),
+            self.building.clone().unwrap_or_default(),
+            self.cycle_day.clone().unwrap_or_default(),
+        ]
+    }
+}
+
+#[derive(Default, Serialize)]
+struct GradeRecord {
+    student: String,
+    class: String,
+    course_id: Option<String>,
+    period: Option<String>,
+    teacher: Option<String>,
+    teacher_email: Option<String>,
+    grade: Option<String>,
+    gradebook_average: Option<String>,
+    comments: Vec<String>,
+}
+impl GradeRecord {
+    fn new(student: &Student, course: &Course) -> Self {
+        let grade = course.traditional_grade.as_ref();
+        let teacher = course.faculty.first();
+        GradeRecord {
+            student: student.name.clone(),
+            class: course.name().to_string(),
+            course_id: course.course_id.clone(),
+            period: course.period.clone(),
+            teacher: teacher.map(|f| f.name()),
+            teacher_email: teacher.and_then(|f| f.email.clone()),
+            grade: grade.and_then(|g| g.grade.clone()),
+            gradebook_average: grade
+                .and_then(|g| g.grade_book_average.clone())
+                .or_else(|| course.grade_book_average.clone()),
+            comments: grade.map(|g| g.comments.clone()).unwrap_or_default(),
+        }
+    }
+}
+impl Record for GradeRecord {
+    const HEADERS: &'static [&'static str] = &["Class", "Teacher", "Grade", "Average"];
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.class.clone(),
+            self.teacher.clone().unwrap_or_default(),
+            self.grade.clone().unwrap_or_else(|| "(standards)".into()),
+            self.gradebook_average.clone().unwrap_or_default(),
+        ]
+    }
+}
+
+#[derive(Default, Serialize)]
+struct AssignmentRecord {
+    student: String,
+    class: String,
+    course_id: Option<String>,
+    name: Option<String>,
+    /// YYYY-MM-DD
+    date: Option<String>,
+    score: Option<String>,
+    max_points: Option<String>,
+    /// score out of max_points, when both are numbers
+    percent: Option<f64>,
+    category: Option<String>,
+    weight: Option<String>,
+    graded: Option<bool>,
+    due_today: Option<bool>,
+    comments: Option<String>,
+}
+impl AssignmentRecord {
+    fn new(student: &Student, course: &Course, a: &Assignment) -> Self {
+        let number = |s: &Option<String>| s.as_deref().and_then(|s| s.trim().parse::<f64>().ok());
+        AssignmentRecord {
+            student: student.name.clone(),
+            class: course.name().to_string(),
+            course_id: course.course_id.clone(),
+            name: a.assignment_name.clone(),
+            date: a
+                .assignment_date
+                .as_deref()
+                .and_then(datestr_date)
+                .map(|d| d.format("%Y-%m-%d").to_string()),
+            score: a.score.clone().filter(|s| !s.is_empty()),
+            max_points: a.max_points.clone().filter(|s| !s.is_empty()),
+            percent: match (number(&a.score), number(&a.max_points)) {
+                (Some(score), Some(max)) if max > 0.0 => {
+                    Some((score / max * 10000.0).round() / 100.0)
+                }
+                _ => None,
+            },
+            category: a.category.clone(),
+            weight: a.weight.clone(),
+            graded: a.is_graded,
+            due_today: a.is_due_today,
+            comments: a.comments.clone(),
+        }
+    }
+}
+impl Record for AssignmentRecord {
+    const HEADERS: &'static [&'static str] = &["Class", "Date", "Assignment", "Grade"];
+    fn row(&self) -> Vec<String> {
+        let date = self
+            .date
+            .as_deref()
+            .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok());
+        vec![
+            self.class.clone(),
+            date.map(|d| d.format("%m/%d/%y").to_string())
+                .unwrap_or_default(),
+            self.name.clone().unwrap_or_default(),
+            format!(
+                "{}/{}",
+                self.score.as_deref().unwrap_or_default(),
+                self.max_points.as_deref().unwrap_or_default()
+            ),
+        ]
+    }
+}
+
+/// one per class, plus one for the whole day named "Daily"
+#[derive(Default, Serialize)]
+struct AttendanceRecord {
+    student: String,
+    name: String,
+    period: Option<String>,
+    absences: i32,
+    late_arrivals: Option<i32>,
+    early_dismissals: Option<i32>,
+}
+impl Record for AttendanceRecord {
+    const HEADERS: &'static [&'static str] = &[
+        "Attendance",
+        "Absences",
+        "Late arrivals",
+        "Early dismissals",
+    ];
+    fn row(&self) -> Vec<String> {
+        let count = |n: Option<i32>| n.map(|n| n.to_string()).unwrap_or_default();
+        vec![
+            self.name.clone(),
+            self.absences.to_string(),
+            count(self.late_arrivals),
+            count(self.early_dismissals),
+        ]
+    }
+}
+
+pub async fn run(
+    command: &str,
+    args: &[String],
+    config: &Config,
+    cfg_dir: &Path,
+    profile: Option<String>,
+    as_of: Option<NaiveDate>,
+) -> Result<(), Box<dyn Error>> {
+    let options = parse_options(command, args)?;
+    let userdata = pick_profile(config, profile)?.clone();
+
+    // the keyring wants its own runtime, so it can't be used from in here
+    let hash = {
+        let cfg_dir = cfg_dir.to_path_buf();
+        let userdata = userdata.clone();
+        tokio::task::spawn_blocking(move || stored_hash(cfg_dir, &userdata)).await??
+    };
+    let api =
+        SchoolTool::with_hash(userdata.baseurl.clone(), userdata.username.clone(), hash).await?;
+    let students = api.get_students().await?;
+
+    if command == "whoami" {
+        let mut records = vec![];
+        for s in &students {
+            let record = |b: Option<&crate::schooltool::Building>| WhoamiRecord {
+                profile: userdata.name.clone(),
+                username: userdata.username.clone(),
+                base_url: userdata.baseurl.clone(),
+                student: s.name.clone(),
+                student_guid: s.guid.clone(),
+                building: b.map(|b| b.name.clone()),
+                building_id: b.map(|b| b.id),
+                cycle_day: b.and_then(|b| b.cycle_day.clone()),
+            };
+            records.extend(s.buildings.iter().map(|b| record(Some(b))));
+            if s.buildings.is_empty() {
+                records.push(record(None));
+            }
+        }
+        return output(&records, options.format);
+    }
+
+    let student = pick_student(&students, options.student.as_deref())?;
+    let building = pick_building(student, options.building.as_deref())?;
+    match command {
+        "grades" | "assignments" => {
+            let data_type = if command == "grades" {
+                "Grades"
+            } else {
+                "Assignments"
+            };
+            let mut data = api
+                .quarter_data(data_type, &student.guid, building, None, as_of)
+                .await?;
+            if let Some(quarter) = &options.quarter {
+                let id = pick_quarter(&data.marking_periods, quarter, command == "grades")?;
+                if Some(id) != data.selected_marking_period {
+                    data = api
+                        .quarter_data(data_type, &student.guid, building, Some(id), as_of)
+                        .await?;
+                }
+            }
+
+            if command == "grades" {
+                let records: Vec<GradeRecord> = graded_courses(&data.courses)
+                    .into_iter()
+                    .map(|c| GradeRecord::new(student, c))
+                    .collect();
+                output(&records, options.format)
+            } else {
+                let class = options.class.as_deref().map(str::to_lowercase);
+                let records: Vec<AssignmentRecord> = data
+                    .courses
+                    .iter()
+                    .filter(|c| match class.as_deref() {
+                        Some(class) => c.name().to_lowercase().contains(class),
+                        None => true,
+                    })
+                    .flat_map(|c| {
+                        c.assignments
+                            .iter()
+                            .map(move |a| AssignmentRecord::new(student, c, a))
+                    })
+                    .collect();
+                output(&records, options.format)
+            }
+        }
+        "attendance" => {
+            let quarter = match &options.quarter {
+                Some(quarter) => {
+                    let data = api.attendance(&student.guid, building, None, as_of).await?;
+                    let periods = api
+                        .attendance_marking_periods(&data, &student.guid, building, as_of)
+                        .await?;
+                    Some(pick_quarter(&periods, quarter, false)?)
+                }
+                None => None,
+            };
+            let data = api
+                .attendance(&student.guid, building, quarter, as_of)
+                .await?;
+            let daily = &data.daily_attendance;
+            let mut records = vec![AttendanceRecord {
+                student: student.name.clone(),
+                name: "Daily".into(),
+                period: None,
+                absences: daily.absent(),
+                late_arrivals: Some(daily.late()),
+                early_dismissals: Some(daily.left_early()),
+            }];
+            records.extend(data.attendance_courses.iter().map(|c| AttendanceRecord {
+                student: student.name.clone(),
+                name: c.name().to_string(),
+                period: c.period.clone(),
+                absences: c.absent(),
+                late_arrivals: None,
+                early_dismissals: None,
+            }));
+            output(&records, options.format)
+        }
+        _ => unreachable!(),
+    }
+}
+
+fn output<T: Record>(records: &[T], format: Format) -> Result<(), Box<dyn Error>> {
+    match format {
+        Format::Json => {
+            println!("{}", serde_json::to_string_pretty(records)?);
+            return Ok(());
+        }
+        Format::Table => {
+            let rows: Vec<Vec<String>> = records.iter().map(Record::row).collect();
+            print_table(T::HEADERS, &rows);
+            return Ok(());
+        }
+        _ => (),
+    }
+    let object = |r: &T| -> Result<Map<String, Value>, Box<dyn Error>> {
+        match serde_json::to_value(r)? {
+            Value::Object(o) => Ok(o),
+            _ => Err("records have to be structs".into()),
+        }
+    };
+    // the headers come from an empty record so there's some even when nothing else is printed
+    let headers: Vec<String> = object(&T::default())?.keys().cloned().collect();
+    let mut rows = vec![];
+    for r in records {
+        rows.push(object(r)?.values().map(cell).collect::<Vec<_>>());
+    }
+    match format {
+        Format::Csv => {
+            let quote = |s: &String| {
+                if s.contains([',', '"', '\n', '\r']) {
+                    format!("\"{}\"", s.replace('"', "\"\""))
+                } else {
+                    s.clone()
+                }
+            };
+            for row in std::iter::once(&headers).chain(&rows) {
+                println!("{}", row.iter().map(quote).collect::<Vec<_>>().join(","));
+            }
+        }
+        Format::Tsv => {
+            let clean = |s: &String| s.replace(['\t', '\n', '\r'], " ");
+            for row in std::iter::once(&headers).chain(&rows) {
+                println!("{}", row.iter().map(clean).collect::<Vec<_>>().join("\t"));
+            }
+        }
+        Format::Json | Format::Table => unreachable!(),
+    }
+    Ok(())
+}
+/// how a field looks in a table or csv. lists get joined with "; "
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        Value::Array(items) => items.iter().map(cell).collect::<Vec<_>>().join("; "),
+        v => v.to_string(),
+    }
+}
+
+fn pick_profile(config: &Config, wanted: Option<String>) -> Result<&UserData, String> {
+    let wanted = wanted.or(config.current.clone());
+    let profile = match (&wanted, config.profiles.as_slice()) {
+        (Some(name), profiles) => profiles.iter().find(|p| &p.name == name),
+        (None, [only]) => Some(only),
+        (None, _) => None,
+    };
+    match profile {
+        Some(p) if p.valid => Ok(p),
+        Some(p) => Err(format!(
+            "profile {} isn't logged in, run schoolterm --profile {} first",
+            p.name, p.name
+        )),
+        None if wanted.is_some() => Err(format!("no profile named {}", wanted.unwrap())),
+        None => Err("pick a profile with --profile, or log in by running schoolterm".into()),
+    }
+}
+
+/// the hash that was sealed and saved by the tui
+fn stored_hash(cfg_dir: PathBuf, userdata: &UserData) -> Result<String, String> {
+    let store = SecretStore::new(&cfg_dir);
+    let account = SecretStore::account(&userdata.username, &userdata.baseurl);
+    // an older version's config that couldn't be moved to the secret store, so it's still on disk
+    if !userdata.password.is_empty() {
+        return Ok(encode_password(userdata.password.clone()));
+    }
+    let Some(secret) = store.get(&account) else {
+        return Err(format!(
+            "there's no saved login for {}, run schoolterm --profile {} to log in",
+            userdata.username, userdata.name
+        ));
+    };
+    // stored bare by an older version, the tui locks it up next time it's opened
+    if !secrets::is_sealed(&secret) {
+        return Ok(encode_password(secret));
+    }
+    secrets::unseal(&ask_passphrase()?, &secret).ok_or_else(|| "wrong passphrase".into())
+}
+fn ask_passphrase() -> Result<String, String> {
+    match std::env::var("SCHOOLTERM_PASSPHRASE") {
+        Ok(p) => Ok(p),
+        Err(_) => rpassword::prompt_password("Passphrase: ").map_err(|e| {
+            format!(
+                "couldn't read the passphrase ({}), set SCHOOLTERM_PASSPHRASE",
+                e
+            )
+        }),
+    }
+}
+
+fn pick_student<'a>(students: &'a [Student], wanted: Option<&str>) -> Result<&'a Student, String> {
+    let Some(wanted) = wanted else {
+        return students
+            .first()
+            .ok_or_else(|| "no students on this account".into());
+    };
+    let wanted = wanted.to_lowercase();
+    students
+        .iter()
+        .find(|s| s.name.to_lowercase().contains(&wanted))
+        .ok_or_else(|| format!("no student matching {}", wanted))
+}
+
+/// `quarter` is a number counting from 1 like in the tui, or the marking period's name
+fn pick_quarter(periods: &[MarkingPeriod], quarter: &str, with_final: bool) -> Result<i32, String> {
+    let periods: Vec<&MarkingPeriod> = periods
+        .iter()
+        .filter(|p| p.id.is_some() && (with_final || !p.is_final()))
+        .collect();
+    let found = match quarter.parse::<usize>() {
+        Ok(n) => n.checked_sub(1).and_then(|i| periods.get(i)),
+        Err(_) => periods.iter().find(|p| {
+            p.name
+                .as_deref()
+                .is_some_and(|n| n.trim().eq_ignore_ascii_case(quarter.trim()))
+        }),
+    };
+    found.and_then(|p| p.id).ok_or_else(|| {
+        let names: Vec<&str> = periods.iter().filter_map(|p| p.name.as_deref()).collect();
+        format!("no quarter {}, there's {}", quarter, names.join(", "))
+    })
+}
+
+/// `building` is its id or name, the first one if it isn't given
+fn pick_building(student: &Student, building: Option<&str>) -> Result<Option<i32>, String> {
+    let Some(building) = building else {
+        return Ok(student.buildings.first().map(|b| b.id));
+    };
+    let found = match building.trim().parse::<i32>() {
+        Ok(id) => student.buildings.iter().find(|b| b.id == id),
+        Err(_) => student
+            .buildings
+            .iter()
+            .find(|b| b.name.trim().eq_ignore_ascii_case(building.trim())),
+    };
+    found.map(|b| Some(b.id)).ok_or_else(|| {
+        let names: Vec<String> = student
+            .buildings
+            .iter()
+            .map(|b| format!("{} ({})", b.name, b.id))
+            .collect();
+        format!(
+            "{} has no building {}, there's {}",
+            student.name,
+            building,
+            names.join(", ")
+        )
+    })
+}
+
+fn print_table(headers: &[&str], rows: &[Vec<String>]) {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.chars().count()).collect();
+    for row in rows {
+        for (w, cell) in widths.iter_mut().zip(row) {
+            *w = (*w).max(cell.chars().count());
+        }
+    }
+    let line = |cells: &mut dyn Iterator<Item = &str>| {
+        cells
+            .zip(&widths)
+            .map(|(c, w)| format!("{:w$}", c, w = w))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+    println!("{}", line(&mut headers.iter().copied()));
+    for row in rows {
+        println!("{}", line(&mut row.iter().map(|s| s.as_str())));
+    }
+}
@@ -0,0 +1,203 @@
+// keys are looked up here and turned into actions. the config can rebind any action with
+// "keys": { "quit": ["q", "ctrl+c"], "down": ["j", "down"] }, which replaces its defaults.
+// ctrl+c always quits on top of that, it's the way out everyone knows
+use console_engine::crossterm::event::KeyEvent;
+use console_engine::{KeyCode, KeyModifiers};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    NextWidget,
+    PrevWidget,
+    Up,
+    Down,
+    Left,
+    Right,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+    Select,
+    Back,
+    Quit,
+    Refresh,
+    Search,
+    Sort,
+    ReverseSort,
+}
+const ACTIONS: [(&str, Action); 17] = [
+    ("next_widget", Action::NextWidget),
+    ("prev_widget", Action::PrevWidget),
+    ("up", Action::Up),
+    ("down", Action::Down),
+    ("left", Action::Left),
+    ("right", Action::Right),
+    ("page_up", Action::PageUp),
+    ("page_down", Action::PageDown),
+    ("home", Action::Home),
+    ("end", Action::End),
+    ("select", Action::Select),
+    ("back", Action::Back),
+    ("quit", Action::Quit),
+    ("refresh", Action::Refresh),
+    ("search", Action::Search),
+    ("sort", Action::Sort),
+    ("reverse_sort", Action::ReverseSort),
+];
+impl Action {
+    /// the key widgets understand for this, they only know about arrows and such
+    pub fn key(self) -> Option<KeyEvent> {
+        let code = match self {
+            Action::Up => KeyCode::Up,
+            Action::Down => KeyCode::Down,
+            Action::Left => KeyCode::Left,
+            Action::Right => KeyCode::Right,
+            Action::PageUp => KeyCode::PageUp,
+            Action::PageDown => KeyCode::PageDown,
+            Action::Home => KeyCode::Home,
+            Action::End => KeyCode::End,
+            Action::Select => KeyCode::Enter,
+            Action::Back => KeyCode::Esc,
+            _ => return None,
+        };
+        Some(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: Vec<(KeyCode, KeyModifiers, Action)>,
+}
+impl Default for Keymap {
+    fn default() -> Self {
+        let defaults = [
+            ("next_widget", &["tab"][..]),
+            ("prev_widget", &["shift+tab"]),
+            ("up", &["up", "k"]),
+            ("down", &["down", "j"]),
+            ("left", &["left", "h"]),
+            ("right", &["right", "l"]),
+            ("page_up", &["pageup"]),
+            ("page_down", &["pagedown"]),
+            ("home", &["home"]),
+            ("end", &["end"]),
+            ("select", &["enter"]),
+            ("back", &["esc"]),
+            ("quit", &["q", "ctrl+c"]),
+            ("refresh", &["r"]),
+            ("search", &["/"]),
+            ("sort", &["s"]),
+            ("reverse_sort", &["S"]),
+        ];
+        let mut keymap = Keymap { bindings: vec![] };
+        for (action, keys) in defaults {
+            let keys: Vec<String> = keys.iter().map(|k| k.to_string()).collect();
+            keymap.bind(action, &keys).unwrap();
+        }
+        keymap
+    }
+}
+impl Keymap {
+    pub fn new(overrides: &HashMap<String, Vec<String>>) -> Result<Self, String> {
+        let mut keymap = Keymap::default();
+        for (action, keys) in overrides {
+            keymap.bind(action, keys)?;
+        }
+        Ok(keymap)
+    }
+    fn bind(&mut self, action: &str, keys: &[String]) -> Result<(), String> {
+        let action = ACTIONS
+            .iter()
+            .find(|(name, _)| *name == action)
+            .map(|(_, a)| *a)
+            .ok_or_else(|| format!("unknown action {} in the key bindings", action))?;
+        self.bindings.retain(|(_, _, a)| *a != action);
+        for key in keys {
+            let (code, modifiers) = parse_key(key)?;
+            self.bindings.push((code, modifiers, action));
+        }
+        Ok(())
+    }
+    pub fn action(&self, key: &KeyEvent) -> Option<Action> {
+        let (code, modifiers) = normalize(key.code, key.modifiers);
+        if code == KeyCode::Char('c') && modifiers == KeyModifiers::CONTROL {
+            return Some(Action::Quit);
+        }
+        self.bindings
+            .iter()
+            .find(|(c, m, _)| *c == code && *m == modifiers)
+            .map(|(_, _, a)| *a)
+    }
+    /// what to tell the user to press
+    pub fn key_name(&self, action: Action) -> String {
+        let Some((code, modifiers, _)) = self.bindings.iter().find(|(_, _, a)| *a == action) else {
+            return "(unbound)".into();
+        };
+        let mut name = String::new();
+        if modifiers.contains(KeyModifiers::CONTROL) {
+            name += "ctrl+";
+        }
+        if modifiers.contains(KeyModifiers::ALT) {
+            name += "alt+";
+        }
+        match code {
+            KeyCode::Char(' ') => name += "space",
+            KeyCode::Char(c) => name.push(*c),
+            KeyCode::BackTab => name += "shift+tab",
+            KeyCode::F(n) => name += &format!("f{}", n),
+            code => name += &format!("{:?}", code).to_lowercase(),
+        }
+        name
+    }
+}
+
+// shift is already in the char, and shift+tab comes in as its own key
+fn normalize(code: KeyCode, modifiers: KeyModifiers) -> (KeyCode, KeyModifiers) {
+    match code {
+        KeyCode::Char(_) | KeyCode::BackTab => (code, modifiers - KeyModifiers::SHIFT),
+        _ => (code, modifiers),
+    }
+}
+fn parse_key(key: &str) -> Result<(KeyCode, KeyModifiers), String> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut rest = key;
+    // "+" on its own is a key, not a separator
+    while let Some((modifier, after)) = rest.split_once('+').filter(|(_, a)| !a.is_empty()) {
+        modifiers |= match modifier.to_lowercase().as_str() {
+            "ctrl" | "control" => KeyModifiers::CONTROL,
+            "alt" => KeyModifiers::ALT,
+            "shift" => KeyModifiers::SHIFT,
+            _ => return Err(format!("unknown modifier {} in key {}", modifier, key)),
+        };
+        rest = after;
+    }
+    let code = match rest.to_lowercase().as_str() {
+        "tab" if modifiers.contains(KeyModifiers::SHIFT) => KeyCode::BackTab,
+        "tab" => KeyCode::Tab,
+        "backtab" => KeyCode::BackTab,
+        "enter" | "return" => KeyCode::Enter,
+        "esc" | "escape" => KeyCode::Esc,
+        "space" => KeyCode::Char(' '),
+        "backspace" => KeyCode::Backspace,
+        "delete" | "del" => KeyCode::Delete,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "pageup" | "pgup" => KeyCode::PageUp,
+        "pagedown" | "pgdn" => KeyCode::PageDown,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        f if f.len() > 1 && f.starts_with('f') && f[1..].parse::<u8>().is_ok() => {
+            KeyCode::F(f[1..].parse().unwrap())
+        }
+        _ => {
+            let mut chars = rest.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => KeyCode::Char(c),
+                _ => return Err(format!("unknown key {}", key)),
+            }
+        }
+    };
+    Ok(normalize(code, modifiers))
+}
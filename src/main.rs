@@ -1,91 +1,351 @@
+mod cli;
+mod keymap;
 mod schooltool;
+mod secrets;
+mod theme;
 mod tui;
-use chrono::{DateTime, Utc};
-use console_engine::crossterm::event::{self, KeyEvent};
+use chrono::{DateTime, Local, NaiveDate, NaiveTime, Utc};
+use console_engine::crossterm::event;
 use console_engine::events::Event;
-use console_engine::forms::FormField;
 
 use console_engine::pixel::{self};
 
-use console_engine::{Color, KeyCode, KeyModifiers};
-use home;
-use schooltool::{SchoolTool, Student};
+use console_engine::Color;
+use keymap::{Action, Keymap};
+use schooltool::{
+    encode_password, same_cycle_day, ApiError, Assignment, AttendanceData, Course, Discussion,
+    MarkingPeriod, Notification, ScheduleCourse, ScheduleData, SchoolTool, Student,
+};
+use secrets::SecretStore;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
 
+use std::collections::HashMap;
 use std::fmt::Display;
 use std::fs;
-use std::future::Future;
-use std::path::PathBuf;
+use std::path::Path;
 
 use std::thread::{self};
 use std::time::UNIX_EPOCH;
 use std::{error::Error, time::Duration};
 use termsize::{self, Size};
 
+use theme::Themes;
 use tokio::sync::mpsc::{self, Receiver};
 use tokio::sync::oneshot;
-use tui::{AsWidget, Rect, Theme, Tui, Widget};
+use tui::{AsWidget, Rect, Tui};
 
-pub const MARKINGPERIODIDS: [u16; 4] = [592, 591, 590, 589];
+/// what's saved in ~/.config/schoolterm
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+struct Config {
+    #[serde(default)]
+    profiles: Vec<UserData>,
+    /// the profile that was open last, it gets opened again without asking
+    #[serde(default)]
+    current: Option<String>,
+    #[serde(default)]
+    theme: Option<String>,
+    /// action name to the keys for it, see keymap.rs
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    keys: HashMap<String, Vec<String>>,
+}
+// configs from before profiles were one bare UserData
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ConfigFile {
+    Single(UserData),
+    Profiles(Config),
+}
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct UserData {
+    #[serde(default = "default_profile_name")]
+    name: String,
     baseurl: String,
     username: String,
+    /// only ever holds the cleartext until it's hashed, from the login form or an old config.
+    /// the hash is what's kept, sealed in the secret store
+    #[serde(default, skip_serializing)]
     password: String,
     valid: bool,
+    /// notifications that have been opened, see `Notification::key`
+    #[serde(default)]
+    seen_notifications: Vec<String>,
 }
 #[tokio::main]
 pub async fn main() -> Result<(), Box<dyn Error>> {
-    fs::create_dir_all(home::home_dir().unwrap().join(".config"))?;
-    let cfg_file = home::home_dir().unwrap().join(".config").join("schoolterm");
-    let (tx, rx) = tokio::sync::mpsc::channel(32);
+    let cfg_dir = home::home_dir().unwrap().join(".config");
+    fs::create_dir_all(&cfg_dir)?;
+    let cfg_file = cfg_dir.join("schoolterm");
+    let mut as_of = None;
+    let mut profile = None;
+    // a subcommand prints something and exits instead of opening the tui
+    let mut command = None;
+    let mut command_args = vec![];
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--as-of" => {
+                let date = args.next().ok_or("--as-of needs a date (YYYY-MM-DD)")?;
+                as_of = Some(parse_date(&date)?);
+            }
+            "--profile" => profile = Some(args.next().ok_or("--profile needs a name")?),
+            _ if command.is_some() => command_args.push(arg),
+            c if cli::COMMANDS.contains(&c) => command = Some(arg),
+            _ => Err(format!("unknown argument: {}", arg))?,
+        }
+    }
 
+    // top thread needs to be std::thread, bottom needs to be tokio. why? i don't fucking know
+    let runtime = tokio::runtime::Handle::current();
+    let mut config = {
+        let (cfg_file, cfg_dir) = (cfg_file.clone(), cfg_dir.clone());
+        // the keyring wants its own runtime, so it can't be used from in here
+        tokio::task::spawn_blocking(move || {
+            get_config(&cfg_file, &SecretStore::new(&cfg_dir)).map_err(|e| e.to_string())
+        })
+        .await??
+    };
+    if let Some(command) = command {
+        return cli::run(&command, &command_args, &config, &cfg_dir, profile, as_of).await;
+    }
+    let mut themes = Themes::load(
+        &cfg_dir.join("schoolterm-themes.toml"),
+        config.theme.as_deref(),
+    )?;
+    let keymap = Keymap::new(&config.keys)?;
+    if let Some(name) = profile.as_ref() {
+        if !config.profiles.iter().any(|p| &p.name == name) {
+            Err(format!(
+                "no profile named {}, run schoolterm without --profile to make one",
+                name
+            ))?;
+        }
+    }
     let t1 = thread::spawn(move || {
-        let mut userdata = get_userdata(&cfg_file).unwrap();
+        let secrets = SecretStore::new(&cfg_dir);
+        let mut t = Tui::new(themes.current().clone(), keymap).unwrap();
+        // kept across profiles so it's only asked for once. SCHOOLTERM_PASSPHRASE skips asking at all
+        let mut passphrase = std::env::var("SCHOOLTERM_PASSPHRASE").ok();
+        let mut wanted = profile.or(config.current.clone());
 
-        tui_thread(&mut userdata, tx);
-        fs::write(cfg_file, serde_json::to_string(&userdata).unwrap()).unwrap();
-    });
+        // logging in again (or as someone else) replaces the session in here
+        let (tx, rx) = mpsc::channel(32);
+        runtime.spawn(net_thread(rx));
 
-    // top thread needs to be std::thread, bottom needs to be tokio. why? i don't fucking know
-    tokio::spawn(async {
-        net_thread(rx).await;
+        loop {
+            // the current profile can be gone if the config was edited, so that gets asked too
+            let known = wanted
+                .take()
+                .and_then(|name| config.profiles.iter().position(|p| p.name == name));
+            let index = match known {
+                Some(i) => i,
+                None => match choose_profile(&mut t, &mut config) {
+                    Some(i) => i,
+                    None => break,
+                },
+            };
+            config.current = Some(config.profiles[index].name.clone());
+
+            let userdata = &mut config.profiles[index];
+            let result = tui_thread(
+                &mut t,
+                userdata,
+                &secrets,
+                &mut passphrase,
+                &mut themes,
+                tx.clone(),
+                as_of,
+            );
+            match result {
+                Err(e) if e.is::<SwitchProfile>() => config.current = None,
+                // straight back to the login form
+                Err(e) if e.is::<LogOut>() => {
+                    let account = SecretStore::account(&userdata.username, &userdata.baseurl);
+                    if let Err(e) = secrets.delete(&account) {
+                        eprintln!("couldn't forget the saved login: {}", e);
+                    }
+                    wanted = config.current.clone();
+                }
+                _ => break,
+            }
+        }
+        drop(t);
+        config.theme = Some(themes.current().name.clone());
+        fs::write(cfg_file, serde_json::to_string(&config).unwrap()).unwrap();
     });
 
     t1.join().unwrap();
 
     Ok(())
 }
-fn get_userdata(file: &PathBuf) -> Result<UserData, Box<dyn Error>> {
-    let Ok(contents) = fs::read_to_string(file) else {return Ok(UserData::default())};
-    Ok(serde_json::from_str(&contents)?)
+fn parse_date(date: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+        .map_err(|_| format!("{} isn't a date, use YYYY-MM-DD", date.trim()))
+}
+fn default_profile_name() -> String {
+    "default".into()
+}
+fn get_config(file: &Path, secrets: &SecretStore) -> Result<Config, Box<dyn Error>> {
+    let Ok(contents) = fs::read_to_string(file) else {
+        return Ok(Config::default());
+    };
+    let mut config = match serde_json::from_str(&contents)? {
+        ConfigFile::Single(userdata) => Config {
+            current: Some(userdata.name.clone()),
+            profiles: vec![userdata],
+            theme: None,
+            keys: HashMap::new(),
+        },
+        ConfigFile::Profiles(config) => config,
+    };
+    // older versions kept the password in here. it goes to the secret store as is and gets off
+    // the disk right away, tui_thread seals it once there's a passphrase
+    let mut moved = false;
+    for p in config
+        .profiles
+        .iter_mut()
+        .filter(|p| !p.password.is_empty())
+    {
+        let account = SecretStore::account(&p.username, &p.baseurl);
+        match secrets.set(&account, &p.password) {
+            Ok(()) => {
+                p.password.clear();
+                moved = true;
+            }
+            Err(e) => eprintln!(
+                "couldn't move the password for {} out of the config: {}",
+                p.name, e
+            ),
+        }
+    }
+    // writing it drops the passwords, so not while one of them has nowhere else to be
+    if moved && config.profiles.iter().all(|p| p.password.is_empty()) {
+        fs::write(file, serde_json::to_string(&config)?)?;
+    }
+    Ok(config)
+}
+/// None if the user backed out of it
+fn choose_profile(t: &mut Tui, config: &mut Config) -> Option<usize> {
+    if !config.profiles.is_empty() {
+        let mut options: Vec<String> = config.profiles.iter().map(|p| p.name.clone()).collect();
+        options.push("+ New profile".into());
+        let i = t.choose("Profiles", &options)?;
+        if i < config.profiles.len() {
+            return Some(i);
+        }
+    }
+    let mut error = None;
+    let name = loop {
+        let name = t.prompt_form("New profile", "Profile name:", error, false)?;
+        let name = name.trim().to_string();
+        if name.is_empty() {
+            error = Some("The name can't be empty");
+        } else if config.profiles.iter().any(|p| p.name == name) {
+            error = Some("There's already a profile with that name");
+        } else {
+            break name;
+        }
+    };
+    config.profiles.push(UserData {
+        name,
+        ..Default::default()
+    });
+    Some(config.profiles.len() - 1)
+}
+/// asks for the passphrase until it opens the saved login, None if the user gives up
+fn unlock(t: &mut Tui, sealed: &str, passphrase: &mut Option<String>) -> Option<String> {
+    let mut error = None;
+    loop {
+        if passphrase.is_none() {
+            *passphrase = t.prompt_form(
+                "Passphrase",
+                "Passphrase for your saved login (esc to log in again):",
+                error,
+                true,
+            );
+            passphrase.as_ref()?;
+        }
+        if let Some(hash) = secrets::unseal(passphrase.as_deref().unwrap(), sealed) {
+            return Some(hash);
+        }
+        *passphrase = None;
+        error = Some("Wrong passphrase");
+    }
 }
 #[derive(Debug)]
 struct QuarterDataResponse {
-    quarters: Vec<Quarter>,
-    activequarter: Value,
-    courses: Vec<Value>,
+    quarters: Vec<MarkingPeriod>,
+    activequarter: Option<i32>,
+    data: ViewData,
 }
-#[derive(Debug)]
-struct Quarter {
-    id: Value,
-    name: String,
+/// whatever the table is currently showing, one per entry in the type drawer
+#[derive(Debug, Clone)]
+enum ViewData {
+    Assignments(Vec<Course>),
+    Grades(Vec<Course>),
+    Attendance(AttendanceData),
+    Schedule(ScheduleData),
+    Discussions(Vec<Discussion>),
+    Notifications(Vec<Notification>),
+}
+/// the entries in the type drawer, in order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tab {
+    Assignments,
+    Grades,
+    Attendance,
+    Schedule,
+    Discussions,
+    Notifications,
+}
+const TABS: [Tab; 6] = [
+    Tab::Assignments,
+    Tab::Grades,
+    Tab::Attendance,
+    Tab::Schedule,
+    Tab::Discussions,
+    Tab::Notifications,
+];
+impl Tab {
+    fn name(self) -> &'static str {
+        match self {
+            Tab::Assignments => "Assignments",
+            Tab::Grades => "Grades",
+            Tab::Attendance => "Attendance",
+            Tab::Schedule => "Schedule",
+            Tab::Discussions => "Discussions",
+            Tab::Notifications => "Notifications",
+        }
+    }
 }
 #[derive(Debug)]
 enum Command {
     Login {
         username: String,
-        password: String,
+        password_hash: String,
         baseurl: String,
-        resp: Responder<Option<Student>>,
+        resp: Responder<Result<Vec<Student>, ApiError>>,
     },
     QuarterData {
-        data_type: String,
-        quarter: Value,
-        resp: Responder<QuarterDataResponse>,
+        tab: Tab,
+        student: String,
+        building: Option<i32>,
+        as_of: Option<NaiveDate>,
+        quarter: Option<i32>,
+        resp: Responder<Result<QuarterDataResponse, ApiError>>,
+    },
+    /// fetches a whole thread and marks it as read
+    OpenDiscussion {
+        id: i32,
+        resp: Responder<Result<Discussion, ApiError>>,
+    },
+    DiscussionReply {
+        id: i32,
+        message: String,
+        resp: Responder<Result<Discussion, ApiError>>,
     },
+    /// forgets the session, the next thing has to be a Login
+    Logout,
 }
 
 #[derive(Debug)]
@@ -93,79 +353,187 @@ struct Exit {}
 impl Error for Exit {}
 impl Display for Exit {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "");
-        Ok(())
+        write!(f, "")
+    }
+}
+
+#[derive(Debug)]
+struct LogOut {}
+impl Error for LogOut {}
+impl Display for LogOut {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "")
+    }
+}
+
+#[derive(Debug)]
+struct SwitchProfile {}
+impl Error for SwitchProfile {}
+impl Display for SwitchProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "")
     }
 }
 
 type Responder<T> = oneshot::Sender<T>;
 
-fn tui_thread(userdata: &mut UserData, tx: mpsc::Sender<Command>) -> Result<(), Box<dyn Error>> {
-    let theme = Theme {
-        bg: Color::Rgb {
-            r: 10,
-            g: 10,
-            b: 10,
-        },
-        bg_accent: Color::Black,
-        fg: Color::DarkBlue,
-        fg_accent: Color::Blue,
-        font: Color::White,
-    };
+/// sends a command to the net thread and shows the loading screen until it answers
+fn request<T>(
+    t: &mut Tui,
+    tx: &mpsc::Sender<Command>,
+    command: impl FnOnce(Responder<Result<T, ApiError>>) -> Command,
+) -> Result<Result<T, ApiError>, Box<dyn Error>> {
+    let (resp_tx, resp_rx) = oneshot::channel();
+    tx.blocking_send(command(resp_tx))
+        .map_err(|_| "the network thread went away")?;
+    // giving up on the wait is shown like any other failed request, so it can be retried
+    Ok(t.poll_blocking(resp_rx)?
+        .unwrap_or(Err(ApiError::Cancelled)))
+}
 
-    let mut t = Tui::new()?;
+/// everything loaded for one student, indexed by type drawer then quarter drawer.
+/// a tab's row is sized once its marking periods are known
+struct StudentCache {
+    quarters: Vec<Option<Vec<MarkingPeriod>>>,
+    viewdata: Vec<Vec<Option<ViewData>>>,
+}
+impl StudentCache {
+    fn new() -> Self {
+        StudentCache {
+            quarters: vec![None; TABS.len()],
+            viewdata: vec![vec![]; TABS.len()],
+        }
+    }
+}
 
-    let mut student = None;
-    while !student.is_some() {
-        if !userdata.valid {
-            t.userdata_form(userdata);
+fn tui_thread(
+    t: &mut Tui,
+    userdata: &mut UserData,
+    secrets: &SecretStore,
+    passphrase: &mut Option<String>,
+    themes: &mut Themes,
+    tx: mpsc::Sender<Command>,
+    mut as_of: Option<NaiveDate>,
+) -> Result<(), Box<dyn Error>> {
+    let mut password_hash = None;
+    // whether password_hash still has to be sealed and stored
+    let mut unsaved = false;
+    // an older version's bare password, which stays where it is unless it gets sealed
+    let mut bare = false;
+    if !userdata.password.is_empty() {
+        password_hash = Some(encode_password(std::mem::take(&mut userdata.password)));
+        unsaved = true;
+    } else {
+        // even after a failed login, so a network error doesn't make anyone type it in again
+        let account = SecretStore::account(&userdata.username, &userdata.baseurl);
+        match secrets.get(&account) {
+            Some(secret) if secrets::is_sealed(&secret) => {
+                password_hash = unlock(t, &secret, passphrase)
+            }
+            // stored bare by an older version
+            Some(password) => {
+                password_hash = Some(encode_password(password));
+                unsaved = true;
+                bare = true;
+            }
+            None => (),
         }
-        let (resp_tx, resp_rx) = oneshot::channel();
+    }
+    userdata.valid = password_hash.is_some();
 
-        tx.blocking_send(Command::Login {
+    let mut students = None;
+    let mut login_error = None;
+    while students.is_none() {
+        if !userdata.valid {
+            if !t.userdata_form(userdata, login_error.as_deref()) {
+                return Err(Box::new(Exit {}));
+            }
+            password_hash = Some(encode_password(std::mem::take(&mut userdata.password)));
+            unsaved = true;
+            bare = false;
+        }
+        let login = request(t, &tx, |resp| Command::Login {
             username: userdata.username.clone(),
-            password: userdata.password.clone(),
+            password_hash: password_hash.clone().unwrap_or_default(),
             baseurl: userdata.baseurl.clone(),
-            resp: resp_tx,
-        })
-        .unwrap();
-        student = t.poll_blocking(resp_rx)?;
+            resp,
+        })?;
+        match login {
+            Ok(s) => students = Some(s),
+            Err(e) => login_error = Some(e.to_string()),
+        }
 
-        if student.is_some() {
-            userdata.valid = true;
-        } else {
-            userdata.valid = false;
+        userdata.valid = students.is_some();
+    }
+
+    if unsaved {
+        if passphrase.is_none() {
+            *passphrase = t
+                .prompt_form(
+                    "Passphrase",
+                    "Pick a passphrase to lock your saved login (esc to not save it):",
+                    None,
+                    true,
+                )
+                .filter(|p| !p.is_empty());
+        }
+        let account = SecretStore::account(&userdata.username, &userdata.baseurl);
+        match (&*passphrase, password_hash) {
+            (Some(passphrase), Some(hash)) => {
+                secrets.set(&account, &secrets::seal(passphrase, &hash)?)?
+            }
+            _ if bare => (),
+            _ => secrets.delete(&account)?,
         }
     }
 
-    let student = student.unwrap();
-    let mut quarters: Vec<Option<Vec<Quarter>>> = vec![None, None];
-    let mut courses: Vec<Vec<Option<Vec<Value>>>> = vec![vec![None; 5]; 2];
+    let students: Vec<Student> = students.unwrap();
+    // keyed by student guid, building and the as of date
+    let mut caches: HashMap<(String, Option<i32>, Option<NaiveDate>), StudentCache> =
+        HashMap::new();
+    // what each student's notifications were last loaded as, for pruning the seen list
+    let mut notification_keys: HashMap<String, Vec<String>> = HashMap::new();
 
     // each option has 1
     let mut should_resize = true;
 
     let mut profdisplay = tui::TextDisplay {
         rect: Rect::default(),
-        text: format!(
-            "Logged in as {}. Today is a day {}",
-            student.name,
-            student.cycle_day.unwrap_or_default()
-        ),
+        text: String::new(),
     };
     let mut topdrawer = tui::Drawer {
         rect: Rect::default(),
         index: 0,
         clicked: false,
         clicked_index: 0,
-        buttons: vec!["Exit".into(), "Log out".into(), "Change theme".into()],
+        buttons: vec![
+            "Exit".into(),
+            "Log out".into(),
+            "Switch profile".into(),
+            "Change theme".into(),
+            String::new(),
+        ],
+    };
+    let mut studentdrawer = tui::Drawer {
+        rect: Rect::default(),
+        index: 0,
+        clicked: false,
+        clicked_index: 0,
+        buttons: students.iter().map(|s| s.name.clone()).collect(),
+    };
+    let mut buildingdrawer = tui::Drawer {
+        rect: Rect::default(),
+        index: 0,
+        clicked: false,
+        clicked_index: 0,
+        buttons: vec![],
     };
     let mut typedrawer = tui::Drawer {
         rect: Rect::default(),
         index: 0,
         clicked: false,
         clicked_index: 0,
-        buttons: vec!["Assignments".into(), "Grades".into()],
+        buttons: TABS.iter().map(|t| t.name().to_string()).collect(),
     };
     let mut quarterdrawer = tui::Drawer {
         rect: Rect::default(),
@@ -186,7 +554,38 @@ fn tui_thread(userdata: &mut UserData, tx: mpsc::Sender<Command>) -> Result<(),
         indecies: vec![],
         data: vec![],
         index: 0,
+        scroll: 0,
+        sort: None,
+        filter: String::new(),
+        clicked: false,
+    };
+    // opening a discussion or notification replaces the table with its full text,
+    // discussions also get a reply box
+    let mut detail_tab: Option<usize> = None;
+    let mut thread: Option<Discussion> = None;
+    // enter on an assignment pops its details up over the table, it keeps the keys until esc
+    let mut popup: Option<tui::TextBox> = None;
+    let mut detailview = tui::TextBox {
+        rect: Rect::default(),
+        title: String::new(),
+        lines: vec![],
+        scroll: 0,
     };
+    let mut compose = tui::Input::new("Reply (enter to send, esc to go back)");
+    // shown above the table while picking a date
+    let mut date_picker = false;
+    // shown along the bottom. nothing more gets loaded until it's retried
+    let mut load_error: Option<String> = None;
+    let mut datebox = tui::Input::new("");
+    // shown above the table while the rows are filtered. it stays up after enter, esc clears it
+    let mut searching = false;
+    let mut searchbox = tui::Input::new("");
+    // these get resolved once the widgets are lined up below. the table (or whatever replaced it)
+    // moves down when the date picker or search box are open
+    let mut focus_search = false;
+    let mut focus_table = false;
+    // drop what's shown so it gets loaded again
+    let mut refresh = false;
 
     let mut selected_widget = 0;
     // let mut should_update_table = false;
@@ -196,203 +595,329 @@ fn tui_thread(userdata: &mut UserData, tx: mpsc::Sender<Command>) -> Result<(),
             topdrawer.clicked = false;
             match topdrawer.index {
                 0 => {
-                    panic!();
+                    Err(Exit {})?;
                 }
                 1 => {
                     userdata.valid = false;
-                    Err(Exit {})?;
+                    let _ = tx.blocking_send(Command::Logout);
+                    Err(LogOut {})?;
+                }
+                2 => {
+                    let _ = tx.blocking_send(Command::Logout);
+                    Err(SwitchProfile {})?;
+                }
+                3 => t.theme = themes.next().clone(),
+                4 => {
+                    date_picker = true;
+                    datebox.label = "As of (YYYY-MM-DD, empty for today, esc to cancel)".into();
+                    datebox.clear();
+                    if let Some(date) = as_of {
+                        datebox.value = date.format("%Y-%m-%d").to_string();
+                        datebox.cursor = datebox.value.chars().count();
+                    }
+                    selected_widget = usize::MAX;
                 }
-                2 => {}
                 _ => (),
             }
         }
+        if datebox.submitted {
+            datebox.submitted = false;
+            let date = if datebox.value.trim().is_empty() {
+                Ok(None)
+            } else {
+                parse_date(&datebox.value).map(Some)
+            };
+            match date {
+                Ok(date) => {
+                    as_of = date;
+                    date_picker = false;
+                    detail_tab = None;
+                    thread = None;
+                }
+                Err(e) => datebox.label = format!("{} (esc to cancel)", e),
+            }
+        }
+        topdrawer.buttons[4] = match as_of {
+            Some(date) => format!("As of {}", date.format("%Y-%m-%d")),
+            None => "As of today".into(),
+        };
+
+        if studentdrawer.clicked {
+            studentdrawer.clicked = false;
+            buildingdrawer.clicked_index = 0;
+            buildingdrawer.index = 0;
+            detail_tab = None;
+            thread = None;
+        }
+        if buildingdrawer.clicked {
+            buildingdrawer.clicked = false;
+            detail_tab = None;
+            thread = None;
+        }
+        let student = &students[studentdrawer.clicked_index];
+        buildingdrawer.buttons = student.buildings.iter().map(|b| b.name.clone()).collect();
+        let building = student.buildings.get(buildingdrawer.clicked_index);
+        let building_id = building.map(|b| b.id);
+        let cycle_day = building.and_then(|b| b.cycle_day.as_deref());
+        let cache = caches
+            .entry((student.guid.clone(), building_id, as_of))
+            .or_insert_with(StudentCache::new);
+        let quarters = &mut cache.quarters;
+        let viewdata = &mut cache.viewdata;
+        if refresh {
+            refresh = false;
+            if let Some(data) =
+                viewdata[typedrawer.clicked_index].get_mut(quarterdrawer.clicked_index)
+            {
+                *data = None;
+            }
+        }
+
+        // notifications are loaded up front for the unread count. that's only a nicety, so if it
+        // fails the tab is left empty and tries again once it's opened
+        if quarters[Tab::Notifications as usize].is_none() && load_error.is_none() {
+            let data = request(t, &tx, |resp| Command::QuarterData {
+                quarter: None,
+                student: student.guid.clone(),
+                building: building_id,
+                as_of,
+                tab: Tab::Notifications,
+                resp,
+            })?;
+            if let Ok(QuarterDataResponse {
+                data: ViewData::Notifications(notifications),
+                ..
+            }) = &data
+            {
+                notification_keys.insert(student.guid.clone(), keys(notifications));
+                prune_seen(userdata, &notification_keys, students.len());
+            }
+            viewdata[Tab::Notifications as usize] = vec![data.ok().map(|d| d.data)];
+            // notifications don't have marking periods
+            quarters[Tab::Notifications as usize] = Some(vec![]);
+        }
 
         if let Some(qdat) = &quarters[typedrawer.clicked_index] {
             if quarterdrawer.clicked_index >= qdat.len() {
                 quarterdrawer.clicked_index = 0;
                 quarterdrawer.index = 0;
             }
-            quarterdrawer.buttons = qdat.iter().map(|f| f.name.clone()).collect();
-            if let Some(cdat) = &courses[typedrawer.clicked_index][quarterdrawer.clicked_index] {
-                let classnames = cdat
-                    .iter()
-                    .map(|f| f.get("CourseName").unwrap().as_str().unwrap().to_string());
-                classdrawer.buttons = vec!["All".to_string()];
-                classdrawer.buttons.extend(classnames);
-
+            quarterdrawer.buttons = qdat
+                .iter()
+                .map(|f| f.name.clone().unwrap_or_default())
+                .collect();
+            if let Some(cdat) = viewdata[typedrawer.clicked_index]
+                .get(quarterdrawer.clicked_index)
+                .and_then(|d| d.as_ref())
+            {
                 table.data = vec![];
-
-                if typedrawer.clicked_index == 0 {
-                    if classdrawer.clicked_index != 0 {
-                        table.indecies = vec!["Assignment".into(), "Date".into(), "Grade".into()];
-
-                        let class = &cdat[classdrawer.clicked_index - 1];
-                        for i in class.get("Assignments").unwrap().as_array().unwrap() {
-                            let mut row = vec![];
-
-                            row.push(
-                                i.get("AssignmentName")
-                                    .unwrap()
-                                    .as_str()
-                                    .unwrap()
-                                    .to_string(),
-                            );
-
-                            let datestr = i
-                                .get("AssignmentDate")
-                                .unwrap()
-                                .as_str()
-                                .unwrap()
-                                .to_string();
-
-                            row.push(parse_datestr(datestr));
-                            row.push(
-                                format!(
-                                    "{}/{}",
-                                    i.get("Score").unwrap().as_str().unwrap(),
-                                    i.get("MaxPoints").unwrap().as_str().unwrap()
-                                )
-                                .to_string(),
-                            );
-
-                            table.data.push(row);
-                        }
-                    } else {
-                        table.indecies = vec![
-                            "Class".into(),
-                            "Date".into(),
-                            "Assignment".into(),
-                            "Grade".into(),
-                        ];
-                        for course in cdat {
-                            for i in course.get("Assignments").unwrap().as_array().unwrap() {
-                                let mut row = vec![];
-                                row.push(
-                                    course
-                                        .get("CourseName")
-                                        .unwrap()
-                                        .as_str()
-                                        .unwrap()
-                                        .to_string(),
-                                );
-                                let datestr = i
-                                    .get("AssignmentDate")
-                                    .unwrap()
-                                    .as_str()
-                                    .unwrap()
-                                    .to_string();
-
-                                row.push(parse_datestr(datestr));
-                                row.push(
-                                    i.get("AssignmentName")
-                                        .unwrap()
-                                        .as_str()
-                                        .unwrap()
-                                        .to_string(),
-                                );
-
-                                row.push(
-                                    format!(
-                                        "{}/{}",
-                                        i.get("Score").unwrap().as_str().unwrap(),
-                                        i.get("MaxPoints").unwrap().as_str().unwrap()
-                                    )
-                                    .to_string(),
-                                );
-
-                                table.data.push(row);
-                            }
-                        }
+                match cdat {
+                    ViewData::Assignments(courses) => {
+                        fill_assignments(&mut table, &mut classdrawer, courses)
                     }
-                } else if typedrawer.clicked_index == 1 {
-                    classdrawer.buttons = vec![];
-                    table.indecies = vec![
-                        "Class".into(),
-                        "Email".into(),
-                        "Comments".into(),
-                        "Grade".into(),
-                    ];
-                    for course in cdat {
-                        let mut row = vec![];
-                        row.push(
-                            course
-                                .get("CourseName")
-                                .unwrap()
-                                .as_str()
-                                .unwrap()
-                                .to_string(),
-                        );
-                        row.push(
-                            course.get("Faculty").unwrap().as_array().unwrap()[0]
-                                .get("Email")
-                                .unwrap()
-                                .as_str()
-                                .unwrap()
-                                .to_string(),
-                        );
-                        let Some(gradeobj) = course.get("TraditionalGrade") else { continue };
-                        if gradeobj.is_null() {
-                            continue;
-                        }
-                        row.push(
-                            gradeobj
-                                .get("Comments")
-                                .unwrap()
-                                .as_array()
-                                .unwrap()
-                                .iter()
-                                .map(|v| v.as_str().unwrap().to_string())
-                                .reduce(|s, acc| format!("{} | {}", s, acc))
-                                .unwrap_or_default(),
-                        );
-                        if let Some(grade) = gradeobj.get("Grade").unwrap().as_str() {
-                            row.push(grade.to_string());
-                        } else {
-                            let avg = gradeobj
-                                .get("GradeBookAverage")
-                                .unwrap()
-                                .as_str()
-                                .unwrap()
-                                .to_string();
-                            row.push(avg);
-                        }
-
-                        table.data.push(row);
+                    ViewData::Grades(courses) => fill_grades(&mut table, &mut classdrawer, courses),
+                    ViewData::Attendance(attendance) => {
+                        fill_attendance(&mut table, &mut classdrawer, attendance)
+                    }
+                    ViewData::Schedule(schedule) => {
+                        fill_schedule(&mut table, &mut classdrawer, schedule, cycle_day)
                     }
+                    ViewData::Discussions(discussions) => {
+                        fill_discussions(&mut table, &mut classdrawer, discussions)
+                    }
+                    ViewData::Notifications(notifications) => fill_notifications(
+                        &mut table,
+                        &mut classdrawer,
+                        notifications,
+                        &userdata.seen_notifications,
+                    ),
                 }
+            } else if load_error.is_some() {
+                // nothing to show until it's retried
+                table.data = vec![];
             } else {
-                let (resp_tx, resp_rx) = oneshot::channel();
-                tx.blocking_send(Command::QuarterData {
-                    quarter: qdat[quarterdrawer.clicked_index].id.clone(),
-                    data_type: data_from_index_scuffed_please_refactor(typedrawer.clicked_index),
-                    resp: resp_tx,
-                })
-                .unwrap();
-                courses[typedrawer.clicked_index][quarterdrawer.clicked_index] =
-                    Some(t.poll_blocking(resp_rx).unwrap().courses);
+                let data = match request(t, &tx, |resp| Command::QuarterData {
+                    quarter: qdat.get(quarterdrawer.clicked_index).and_then(|q| q.id),
+                    student: student.guid.clone(),
+                    building: building_id,
+                    as_of,
+                    tab: TABS[typedrawer.clicked_index],
+                    resp,
+                })? {
+                    Ok(data) => data,
+                    Err(e) => {
+                        load_error = Some(e.to_string());
+                        table.data = vec![];
+                        continue;
+                    }
+                };
+                if let ViewData::Notifications(notifications) = &data.data {
+                    notification_keys.insert(student.guid.clone(), keys(notifications));
+                    prune_seen(userdata, &notification_keys, students.len());
+                }
+                viewdata[typedrawer.clicked_index][quarterdrawer.clicked_index] = Some(data.data);
             }
+        } else if load_error.is_some() {
+            table.data = vec![];
         } else {
-            let (resp_tx, resp_rx) = oneshot::channel();
-            tx.blocking_send(Command::QuarterData {
-                quarter: Value::Null,
-                data_type: data_from_index_scuffed_please_refactor(typedrawer.clicked_index),
-                resp: resp_tx,
-            })
-            .unwrap();
-
-            let data = t.poll_blocking(resp_rx).unwrap();
+            let mut data = match request(t, &tx, |resp| Command::QuarterData {
+                quarter: None,
+                student: student.guid.clone(),
+                building: building_id,
+                as_of,
+                tab: TABS[typedrawer.clicked_index],
+                resp,
+            })? {
+                Ok(data) => data,
+                Err(e) => {
+                    load_error = Some(e.to_string());
+                    table.data = vec![];
+                    continue;
+                }
+            };
+            // marking periods without an id can't be asked for
+            data.quarters.retain(|q| q.id.is_some());
+            if !matches!(data.data, ViewData::Grades(_)) {
+                data.quarters.retain(|q| !q.is_final());
+            }
 
             let activequarter = data
                 .quarters
                 .iter()
-                .position(|q| q.id == data.activequarter)
-                .unwrap();
-            courses[typedrawer.clicked_index][activequarter] = Some(data.courses);
+                .position(|q| data.activequarter.is_some() && q.id == data.activequarter)
+                .or_else(|| {
+                    data.quarters
+                        .iter()
+                        .position(|q| q.is_current == Some(true))
+                })
+                .unwrap_or_default();
+            if let (ViewData::Schedule(schedule), Some(today)) = (&data.data, cycle_day) {
+                // start on today's rotation
+                if let Some(i) = schedule
+                    .cycle_days()
+                    .iter()
+                    .position(|d| same_cycle_day(d, today))
+                {
+                    classdrawer.clicked_index = i;
+                    classdrawer.index = i;
+                }
+            }
+            // tabs without marking periods still keep their data in the first slot
+            let mut row = vec![None; data.quarters.len().max(1)];
+            row[activequarter] = Some(data.data);
+            viewdata[typedrawer.clicked_index] = row;
             quarters[typedrawer.clicked_index] = Some(data.quarters);
             quarterdrawer.clicked_index = activequarter;
             quarterdrawer.index = activequarter;
         }
 
+        let unread = match viewdata[Tab::Notifications as usize].first() {
+            Some(Some(ViewData::Notifications(notifications))) => notifications
+                .iter()
+                .filter(|n| !userdata.seen_notifications.contains(&n.key()))
+                .count(),
+            _ => 0,
+        };
+        profdisplay.text = format!(
+            "Logged in as {}. Today is a day {}",
+            student.name,
+            cycle_day.unwrap_or("?")
+        );
+        if unread > 0 {
+            profdisplay.text += &format!(" [{} new notifications]", unread);
+        }
+
+        if detail_tab.is_some_and(|i| i != typedrawer.clicked_index) {
+            detail_tab = None;
+            thread = None;
+        }
+        if table.clicked {
+            table.clicked = false;
+            let view = viewdata[typedrawer.clicked_index]
+                .get_mut(quarterdrawer.clicked_index)
+                .and_then(|d| d.as_mut());
+            if let Some(ViewData::Assignments(courses)) = view {
+                let rows = assignment_rows(courses, classdrawer.clicked_index);
+                if let Some((course, assignment)) = table.selected().and_then(|i| rows.get(i)) {
+                    popup = Some(assignment_popup(course, assignment));
+                }
+            }
+            if let Some(ViewData::Grades(courses)) = view {
+                if let Some(course) = table
+                    .selected()
+                    .and_then(|i| graded_courses(courses).get(i).copied())
+                {
+                    show_grade(&mut detailview, course);
+                    detail_tab = Some(typedrawer.clicked_index);
+                    focus_table = true;
+                }
+            }
+            if let Some(ViewData::Notifications(notifications)) = view {
+                if let Some(n) = table.selected().and_then(|i| notifications.get(i)) {
+                    show_notification(&mut detailview, n);
+                    if !userdata.seen_notifications.contains(&n.key()) {
+                        userdata.seen_notifications.push(n.key());
+                    }
+                    detail_tab = Some(typedrawer.clicked_index);
+                    focus_table = true;
+                }
+            }
+            if let Some(ViewData::Discussions(discussions)) = view {
+                if let Some(d) = table.selected().and_then(|i| discussions.get_mut(i)) {
+                    match request(t, &tx, |resp| Command::OpenDiscussion { id: d.id, resp })? {
+                        Ok(opened) => *d = opened,
+                        Err(e) => {
+                            load_error = Some(e.to_string());
+                            continue;
+                        }
+                    }
+                    d.notification_count = Some(0);
+                    show_thread(&mut detailview, d);
+                    thread = Some(d.clone());
+                    detail_tab = Some(typedrawer.clicked_index);
+                    focus_table = true;
+                }
+            }
+        }
+        if compose.submitted {
+            compose.submitted = false;
+            if let Some(d) = &thread {
+                if !compose.value.trim().is_empty() {
+                    let d = match request(t, &tx, |resp| Command::DiscussionReply {
+                        id: d.id,
+                        message: compose.value.clone(),
+                        resp,
+                    })? {
+                        Ok(d) => d,
+                        // the reply stays in the box so it can be sent again
+                        Err(e) => {
+                            load_error = Some(e.to_string());
+                            continue;
+                        }
+                    };
+                    show_thread(&mut detailview, &d);
+                    thread = Some(d);
+                    compose.clear();
+                }
+            }
+        }
+
+        if searchbox.submitted {
+            searchbox.submitted = false;
+            searching = !searchbox.value.trim().is_empty();
+            focus_table = true;
+        }
+        if searching {
+            table.filter = searchbox.value.clone();
+            searchbox.label = format!(
+                "Search: {} of {} rows (esc to clear)",
+                table.order().len(),
+                table.data.len()
+            );
+        } else {
+            table.filter.clear();
+        }
+
         if should_resize {
             // can be a self. later
             let top_h = 5;
@@ -419,6 +944,20 @@ fn tui_thread(userdata: &mut UserData, tx: mpsc::Sender<Command>) -> Result<(),
                 topdrawer.rect.h = top_h;
                 topdrawer.rect.w = t.size.cols as u32 - profdisplay.rect.w;
             }
+            if students.len() > 1 {
+                studentdrawer.rect.y = current_y;
+                studentdrawer.rect.x = 0;
+                studentdrawer.rect.w = t.size.cols as u32;
+                studentdrawer.rect.h = 3;
+                current_y += 3;
+            }
+            if student.buildings.len() > 1 {
+                buildingdrawer.rect.y = current_y;
+                buildingdrawer.rect.x = 0;
+                buildingdrawer.rect.w = t.size.cols as u32;
+                buildingdrawer.rect.h = 3;
+                current_y += 3;
+            }
             typedrawer.rect.y = current_y;
             typedrawer.rect.x = 0;
             typedrawer.rect.w = t.size.cols as u32;
@@ -446,32 +985,106 @@ fn tui_thread(userdata: &mut UserData, tx: mpsc::Sender<Command>) -> Result<(),
             classdrawer.rect.h = 3;
             quarterdrawer.rect.x = 0;
 
+            if date_picker {
+                datebox.rect.y = current_y;
+                datebox.rect.x = 0;
+                datebox.rect.w = t.size.cols as u32;
+                datebox.rect.h = 3;
+                current_y += 3;
+            }
+            if searching {
+                searchbox.rect.y = current_y;
+                searchbox.rect.x = 0;
+                searchbox.rect.w = t.size.cols as u32;
+                searchbox.rect.h = 3;
+                current_y += 3;
+            }
             table.rect.y = current_y;
             table.rect.x = 0;
             table.rect.w = t.size.cols as u32;
-            table.rect.h = t.size.rows as u32 - current_y;
+            table.rect.h = t.size.rows as u32 - current_y - load_error.is_some() as u32;
+
+            detailview.rect = table.rect.clone();
+            if thread.is_some() {
+                detailview.rect.h = table.rect.h.saturating_sub(3);
+            }
+            compose.rect = table.rect.clone();
+            compose.rect.y = table.rect.y + detailview.rect.h;
+            compose.rect.h = 3;
+
+            if let Some(popup) = &mut popup {
+                popup.rect.w = (t.size.cols as u32 * 2 / 3).max(40).min(t.size.cols as u32);
+                popup.rect.h = (t.size.rows as u32 / 2).max(12).min(t.size.rows as u32);
+                popup.rect.x = (t.size.cols as u32 - popup.rect.w) / 2;
+                popup.rect.y = (t.size.rows as u32 - popup.rect.h) / 2;
+            }
         }
 
-        let mut vcs = vec![
-            profdisplay.as_widget(),
-            topdrawer.as_widget(),
+        let mut vcs = vec![profdisplay.as_widget(), topdrawer.as_widget()];
+        if students.len() > 1 {
+            vcs.push(studentdrawer.as_widget());
+        }
+        if student.buildings.len() > 1 {
+            vcs.push(buildingdrawer.as_widget());
+        }
+        vcs.extend([
             typedrawer.as_widget(),
             quarterdrawer.as_widget(),
             classdrawer.as_widget(),
-            table.as_widget(),
-        ];
+        ]);
+        if date_picker {
+            if selected_widget == usize::MAX {
+                selected_widget = vcs.len();
+            }
+            vcs.push(datebox.as_widget());
+        }
+        if searching {
+            if focus_search {
+                focus_search = false;
+                selected_widget = vcs.len();
+            }
+            vcs.push(searchbox.as_widget());
+        }
+        if focus_table {
+            focus_table = false;
+            selected_widget = vcs.len();
+        }
+        if detail_tab.is_some() {
+            vcs.push(detailview.as_widget());
+            if thread.is_some() {
+                vcs.push(compose.as_widget());
+            }
+        } else {
+            vcs.push(table.as_widget());
+        }
+        if let Some(popup) = &mut popup {
+            selected_widget = vcs.len();
+            vcs.push(popup.as_widget());
+        }
+        selected_widget = selected_widget.min(vcs.len() - 1);
 
         let ev = t.engine.poll();
         match ev {
             Event::Frame => {
                 t.engine.clear_screen();
-                t.engine.fill(pixel::pxl_bg(' ', theme.bg));
+                t.engine.fill(pixel::pxl_bg(' ', t.theme.bg));
 
                 for (i, w) in vcs.iter_mut().enumerate() {
-                    let scr = w.draw(&theme, i == selected_widget);
+                    let scr = w.draw(&t.theme, i == selected_widget);
                     t.engine
                         .print_screen(w.rect().x as i32, w.rect().y as i32, &scr);
                 }
+                if let Some(e) = &load_error {
+                    let banner =
+                        format!(" {} ({} to retry)", e, t.keymap.key_name(Action::Refresh));
+                    t.engine.print_fbg(
+                        0,
+                        t.size.rows as i32 - 1,
+                        &format!("{:width$}", banner, width = t.size.cols as usize),
+                        t.theme.font,
+                        Color::DarkRed,
+                    );
+                }
 
                 t.engine.draw();
             }
@@ -497,39 +1110,57 @@ fn tui_thread(userdata: &mut UserData, tx: mpsc::Sender<Command>) -> Result<(),
                     }
                 }
             }
-            Event::Key(KeyEvent {
-                code: KeyCode::Char('c'),
-                modifiers: KeyModifiers::CONTROL,
-            }) => return Err(Box::new(Exit {})),
             Event::Key(k) => {
-                let k = vcs[selected_widget].feed_event(event::Event::Key(k));
-                let Some(k) = k else{continue};
-                let event::Event::Key(k) = k else {continue};
-                match k {
-                    KeyEvent {
-                        code: KeyCode::Right,
-                        modifiers: KeyModifiers::NONE,
+                // typing goes to the widget first, everything else gets turned into an action
+                let widget = vcs[selected_widget].as_widget();
+                let action = if widget.wants_text() {
+                    match widget.feed_event(event::Event::Key(k)) {
+                        Some(event::Event::Key(k)) => t.keymap.action(&k),
+                        _ => continue,
                     }
-                    | KeyEvent {
-                        code: KeyCode::Down,
-                        modifiers: KeyModifiers::NONE,
-                    } => {
-                        if selected_widget < vcs.len() - 1 {
-                            selected_widget += 1;
+                } else {
+                    let action = t.keymap.action(&k);
+                    match action.and_then(|a| a.key()) {
+                        Some(key) if widget.feed_event(event::Event::Key(key)).is_none() => {
+                            continue
                         }
+                        _ => action,
                     }
-
-                    KeyEvent {
-                        code: KeyCode::Left,
-                        modifiers: KeyModifiers::NONE,
+                };
+                match action {
+                    Some(Action::NextWidget | Action::Right | Action::Down)
+                        if selected_widget < vcs.len() - 1 =>
+                    {
+                        selected_widget += 1;
                     }
-                    | KeyEvent {
-                        code: KeyCode::Up,
-                        modifiers: KeyModifiers::NONE,
-                    } => {
-                        if selected_widget > 0 {
-                            selected_widget -= 1;
-                        }
+                    Some(Action::PrevWidget | Action::Left | Action::Up) if selected_widget > 0 => {
+                        selected_widget -= 1;
+                    }
+                    Some(Action::Back) if popup.is_some() => popup = None,
+                    Some(Action::Back) if date_picker => {
+                        date_picker = false;
+                        focus_table = true;
+                    }
+                    Some(Action::Back) if detail_tab.is_some() => {
+                        detail_tab = None;
+                        thread = None;
+                        focus_table = true;
+                    }
+                    Some(Action::Back) if searching => {
+                        searching = false;
+                        searchbox.clear();
+                        focus_table = true;
+                    }
+                    Some(Action::Quit) => return Err(Box::new(Exit {})),
+                    Some(Action::Search) if detail_tab.is_none() && popup.is_none() => {
+                        searching = true;
+                        focus_search = true;
+                    }
+                    Some(Action::Sort) => table.next_sort(),
+                    Some(Action::ReverseSort) => table.reverse_sort(),
+                    Some(Action::Refresh) => {
+                        load_error = None;
+                        refresh = true;
                     }
                     _ => {}
                 }
@@ -537,91 +1168,614 @@ fn tui_thread(userdata: &mut UserData, tx: mpsc::Sender<Command>) -> Result<(),
         }
     }
 }
-fn parse_datestr(date: String) -> String {
-    let timestamp = &date[6..date.len() - 2];
-    let d = UNIX_EPOCH + Duration::from_millis(timestamp.parse().unwrap());
-    let dt = DateTime::<Utc>::from(d);
-    dt.format("%m/%d/%y").to_string()
+fn fill_assignments(table: &mut tui::Table, classdrawer: &mut tui::Drawer, courses: &[Course]) {
+    classdrawer.buttons = vec!["All".to_string()];
+    classdrawer
+        .buttons
+        .extend(courses.iter().map(|f| f.name().to_string()));
+    if classdrawer.clicked_index >= classdrawer.buttons.len() {
+        classdrawer.clicked_index = 0;
+        classdrawer.index = 0;
+    }
+
+    if classdrawer.clicked_index != 0 {
+        table.indecies = vec!["Assignment".into(), "Date".into(), "Grade".into()];
+
+        for (_, i) in assignment_rows(courses, classdrawer.clicked_index) {
+            table.data.push(vec![
+                i.assignment_name.clone().unwrap_or_default(),
+                i.assignment_date
+                    .as_deref()
+                    .map(parse_datestr)
+                    .unwrap_or_default(),
+                format_score(i),
+            ]);
+        }
+    } else {
+        table.indecies = vec![
+            "Class".into(),
+            "Date".into(),
+            "Assignment".into(),
+            "Grade".into(),
+        ];
+        for (course, i) in assignment_rows(courses, 0) {
+            table.data.push(vec![
+                course.name().to_string(),
+                i.assignment_date
+                    .as_deref()
+                    .map(parse_datestr)
+                    .unwrap_or_default(),
+                i.assignment_name.clone().unwrap_or_default(),
+                format_score(i),
+            ]);
+        }
+    }
+}
+/// the assignments in the order they're put in the table. class 0 is "All"
+fn assignment_rows(courses: &[Course], class: usize) -> Vec<(&Course, &Assignment)> {
+    let courses = match class {
+        0 => courses,
+        _ => courses.get(class - 1..class).unwrap_or_default(),
+    };
+    courses
+        .iter()
+        .flat_map(|c| c.assignments.iter().map(move |a| (c, a)))
+        .collect()
+}
+fn assignment_popup(course: &Course, assignment: &Assignment) -> tui::TextBox {
+    let mut lines = vec![format!("Class: {}", course.name())];
+    let mut due = format!(
+        "Due: {}",
+        assignment
+            .assignment_date
+            .as_deref()
+            .map(parse_datestr)
+            .unwrap_or("?".into())
+    );
+    if assignment.is_due_today == Some(true) {
+        due += " (today)";
+    }
+    lines.push(due);
+    if let Some(category) = &assignment.category {
+        lines.push(format!("Category: {}", category));
+    }
+    if let Some(weight) = &assignment.weight {
+        lines.push(format!("Weight: {}", weight));
+    }
+    lines.push(format!(
+        "Score: {} ({})",
+        format_score(assignment),
+        match assignment.is_graded {
+            Some(true) => "graded",
+            Some(false) => "not graded yet",
+            None if assignment.score.as_deref().is_some_and(|s| !s.is_empty()) => "graded",
+            None => "not graded yet",
+        }
+    ));
+    if let Some(attributes) = assignment.attributes.as_deref().filter(|a| !a.is_empty()) {
+        lines.push(format!("Attributes: {}", attributes));
+    }
+    for (heading, text) in [
+        ("Description", &assignment.assignment_text),
+        ("Teacher comments", &assignment.comments),
+        ("Notes", &assignment.notes),
+    ] {
+        if let Some(text) = text.as_deref().filter(|t| !t.trim().is_empty()) {
+            lines.extend([String::new(), format!("{}:", heading), text.to_string()]);
+        }
+    }
+    tui::TextBox {
+        rect: Rect::default(),
+        title: assignment.assignment_name.clone().unwrap_or_default(),
+        lines,
+        scroll: 0,
+    }
 }
 
-fn data_from_index_scuffed_please_refactor(clicked_index: usize) -> String {
-    match clicked_index {
-        0 => "Assignments",
-        1 => "Grades",
-        _ => todo!(),
+fn fill_grades(table: &mut tui::Table, classdrawer: &mut tui::Drawer, courses: &[Course]) {
+    classdrawer.buttons = vec![];
+    table.indecies = vec![
+        "Class".into(),
+        "Email".into(),
+        "Comments".into(),
+        "Grade".into(),
+    ];
+    for course in graded_courses(courses) {
+        let gradeobj = course.traditional_grade.as_ref();
+        table.data.push(vec![
+            course.name().to_string(),
+            course
+                .faculty
+                .first()
+                .and_then(|f| f.email.clone())
+                .unwrap_or_default(),
+            gradeobj.map(|g| g.comments.join(" | ")).unwrap_or_default(),
+            gradeobj
+                .and_then(|g| g.grade.clone().or_else(|| g.grade_book_average.clone()))
+                .unwrap_or_else(|| "(standards)".into()),
+        ]);
     }
-    .into()
+}
+/// the courses that get a row in the grades table
+fn graded_courses(courses: &[Course]) -> Vec<&Course> {
+    courses
+        .iter()
+        .filter(|c| c.traditional_grade.is_some() || !c.descriptors().is_empty())
+        .collect()
 }
 
-async fn net_thread(mut rx: Receiver<Command>) {
-    let (api, student) = 'collector: loop {
-        if let Some(cmd) = rx.recv().await {
-            match cmd {
-                Command::Login {
-                    username,
-                    password,
-                    baseurl,
-                    resp,
-                } => {
-                    let Ok(api) = SchoolTool::new(baseurl, username, password).await else {
-                        let _ = resp.send(None).unwrap();
-                        continue;
-                    };
-                    let Ok(student) = api.get_student(None).await else {
-                        let _ = resp.send(None).unwrap();
-                        continue;
-                    };
-                    let _ = resp.send(Some(student.clone())).unwrap();
-                    break 'collector (api, student);
-                }
-                _ => panic!(),
+fn fill_attendance(
+    table: &mut tui::Table,
+    classdrawer: &mut tui::Drawer,
+    attendance: &AttendanceData,
+) {
+    classdrawer.buttons = vec!["Summary".to_string(), "Daily".to_string()];
+    classdrawer.buttons.extend(
+        attendance
+            .attendance_courses
+            .iter()
+            .map(|c| c.name().to_string()),
+    );
+    if classdrawer.clicked_index >= classdrawer.buttons.len() {
+        classdrawer.clicked_index = 0;
+        classdrawer.index = 0;
+    }
+
+    let daily = &attendance.daily_attendance;
+    match classdrawer.clicked_index {
+        0 => {
+            table.indecies = vec![
+                "Attendance".into(),
+                "Absences".into(),
+                "Late arrivals".into(),
+                "Early dismissals".into(),
+            ];
+            table.data.push(vec![
+                "Daily".into(),
+                daily.absent().to_string(),
+                daily.late().to_string(),
+                daily.left_early().to_string(),
+            ]);
+            for course in &attendance.attendance_courses {
+                table.data.push(vec![
+                    course.name().to_string(),
+                    course.absent().to_string(),
+                    String::new(),
+                    String::new(),
+                ]);
+            }
+        }
+        1 => {
+            table.indecies = vec!["Date".into(), "Type".into(), "Reason".into()];
+            for a in &daily.absence_list {
+                table.data.push(vec![
+                    a.date_absent
+                        .as_deref()
+                        .map(parse_datestr)
+                        .unwrap_or_default(),
+                    a.record_type.clone().unwrap_or_default(),
+                    a.reason.clone().unwrap_or_default(),
+                ]);
+            }
+        }
+        n => {
+            table.indecies = vec!["Date".into(), "Period".into(), "Reason".into()];
+            let course = &attendance.attendance_courses[n - 2];
+            for a in &course.absence_list {
+                table.data.push(vec![
+                    a.date_absent
+                        .as_deref()
+                        .map(parse_datestr)
+                        .unwrap_or_default(),
+                    course.period.clone().unwrap_or_default(),
+                    a.reason_text
+                        .clone()
+                        .or_else(|| a.reason.clone())
+                        .unwrap_or_default(),
+                ]);
             }
         }
+    }
+}
+
+fn fill_schedule(
+    table: &mut tui::Table,
+    classdrawer: &mut tui::Drawer,
+    schedule: &ScheduleData,
+    today: Option<&str>,
+) {
+    let days = schedule.cycle_days();
+    classdrawer.buttons = days
+        .iter()
+        .map(|d| {
+            let mut label = if d.parse::<u32>().is_ok() {
+                format!("Day {}", d)
+            } else {
+                d.clone()
+            };
+            if today.is_some_and(|t| same_cycle_day(d, t)) {
+                label += " (today)";
+            }
+            label
+        })
+        .collect();
+    if classdrawer.clicked_index >= classdrawer.buttons.len() {
+        classdrawer.clicked_index = 0;
+        classdrawer.index = 0;
+    }
+
+    table.indecies = vec![
+        "".into(),
+        "Period".into(),
+        "Time".into(),
+        "Course".into(),
+        "Room".into(),
+        "Teacher".into(),
+    ];
+    let Some(day) = days.get(classdrawer.clicked_index) else {
+        return;
     };
-    loop {
-        if let Some(cmd) = rx.recv().await {
-            match cmd {
-                Command::QuarterData {
-                    data_type,
-                    quarter,
-                    resp,
-                } => {
-                    let dat = api
-                        .quarter_data(data_type.clone(), student.guid.clone(), quarter)
-                        .await
-                        .unwrap(); //todo!
-                                   //
-                                   // dbg!(&dat);
-                    let quarters = dat
-                        .get("MarkingPeriods")
-                        .unwrap()
-                        .as_array()
-                        .unwrap()
-                        .iter()
-                        .map(|f| Quarter {
-                            id: f.get("Id").unwrap().clone(),
-                            name: f.get("Name").unwrap().as_str().unwrap().into(),
-                        })
-                        .collect();
-
-                    let fieldname = if &data_type == "Assignments" {
-                        "AssignmentCourses"
-                    } else if data_type == "Grades" {
-                        "GradeCourses"
-                    } else {
-                        todo!()
+    let mut courses: Vec<&ScheduleCourse> = schedule
+        .schedule_courses
+        .iter()
+        .filter(|c| c.meets_on(day))
+        .collect();
+    courses.sort_by_key(|c| c.start_time.as_deref().and_then(parse_time));
+
+    // only mark the current period when looking at today's rotation
+    let is_today = today.is_some_and(|t| same_cycle_day(day, t));
+    let now = Local::now().time();
+    let current = courses.iter().position(|c| {
+        let start = c.start_time.as_deref().and_then(parse_time);
+        let end = c.end_time.as_deref().and_then(parse_time);
+        matches!((start, end), (Some(start), Some(end)) if start <= now && now < end)
+    });
+    let next = courses.iter().position(|c| {
+        c.start_time
+            .as_deref()
+            .and_then(parse_time)
+            .is_some_and(|start| start > now)
+    });
+
+    for (i, course) in courses.iter().enumerate() {
+        let marker = if !is_today {
+            ""
+        } else if current == Some(i) {
+            "> now"
+        } else if next == Some(i) {
+            "next"
+        } else {
+            ""
+        };
+        let period = match (&course.period, &course.last_period) {
+            (Some(p), Some(l)) if p != l && !l.is_empty() => format!("{}-{}", p, l),
+            (p, _) => p.clone().unwrap_or_default(),
+        };
+        table.data.push(vec![
+            marker.into(),
+            period,
+            format!(
+                "{} - {}",
+                course.start_time.as_deref().unwrap_or_default(),
+                course.end_time.as_deref().unwrap_or_default()
+            ),
+            course.name().to_string(),
+            course.room.clone().unwrap_or_default(),
+            course
+                .faculty
+                .iter()
+                .map(|f| f.name())
+                .collect::<Vec<_>>()
+                .join(", "),
+        ]);
+    }
+}
+
+fn fill_discussions(
+    table: &mut tui::Table,
+    classdrawer: &mut tui::Drawer,
+    discussions: &[Discussion],
+) {
+    classdrawer.buttons = vec![];
+    table.indecies = vec![
+        "".into(),
+        "Discussion".into(),
+        "With".into(),
+        "Last message".into(),
+    ];
+    for d in discussions {
+        table.data.push(vec![
+            if d.is_unread() { "new" } else { "" }.into(),
+            d.title().to_string(),
+            d.participants(),
+            d.last_message
+                .as_deref()
+                .map(parse_datestr)
+                .unwrap_or_default(),
+        ]);
+    }
+}
+
+fn keys(notifications: &[Notification]) -> Vec<String> {
+    notifications.iter().map(|n| n.key()).collect()
+}
+/// forget about notifications the server doesn't send anymore. every student on the account
+/// shares the seen list, so this waits until all of theirs have been loaded
+fn prune_seen(userdata: &mut UserData, loaded: &HashMap<String, Vec<String>>, students: usize) {
+    if loaded.len() < students {
+        return;
+    }
+    userdata
+        .seen_notifications
+        .retain(|k| loaded.values().any(|keys| keys.contains(k)));
+}
+fn fill_notifications(
+    table: &mut tui::Table,
+    classdrawer: &mut tui::Drawer,
+    notifications: &[Notification],
+    seen: &[String],
+) {
+    classdrawer.buttons = vec![];
+    table.indecies = vec!["".into(), "Date".into(), "From".into(), "Subject".into()];
+    for n in notifications {
+        table.data.push(vec![
+            if seen.contains(&n.key()) { "" } else { "new" }.into(),
+            n.notification_date
+                .as_deref()
+                .map(parse_datestr)
+                .unwrap_or_default(),
+            n.from.clone().unwrap_or_default(),
+            n.subject.clone().unwrap_or_default(),
+        ]);
+    }
+}
+
+fn show_notification(detailview: &mut tui::TextBox, notification: &Notification) {
+    detailview.title = notification.subject.clone().unwrap_or_default();
+    detailview.lines = vec![
+        format!(
+            "From {} on {}",
+            notification.from.as_deref().unwrap_or("?"),
+            notification
+                .notification_date
+                .as_deref()
+                .map(parse_datestr)
+                .unwrap_or_default()
+        ),
+        String::new(),
+        notification.message.clone().unwrap_or_default(),
+    ];
+    detailview.scroll = 0;
+}
+
+fn show_grade(detailview: &mut tui::TextBox, course: &Course) {
+    detailview.title = course.name().to_string();
+    detailview.lines = vec![];
+    let gradeobj = course.traditional_grade.as_ref();
+    if let Some(grade) = gradeobj.and_then(|g| g.grade.as_deref()) {
+        detailview.lines.push(format!("Grade: {}", grade));
+    }
+    let average = gradeobj
+        .and_then(|g| g.grade_book_average.as_deref())
+        .or(course.grade_book_average.as_deref());
+    if let Some(average) = average.filter(|a| !a.is_empty()) {
+        detailview
+            .lines
+            .push(format!("Gradebook average: {}", average));
+    }
+    for f in &course.faculty {
+        detailview.lines.push(match &f.email {
+            Some(email) => format!("Teacher: {} <{}>", f.name(), email),
+            None => format!("Teacher: {}", f.name()),
+        });
+    }
+    let comments = gradeobj.map(|g| g.comments.as_slice()).unwrap_or_default();
+    if !comments.is_empty() {
+        detailview.lines.extend([String::new(), "Comments:".into()]);
+        detailview.lines.extend(comments.iter().cloned());
+    }
+    let descriptors = course.descriptors();
+    if !descriptors.is_empty() {
+        detailview
+            .lines
+            .extend([String::new(), "Standards:".into()]);
+        for d in descriptors {
+            let mark = d.alpha_key.as_deref().or(d.score.as_deref()).unwrap_or("-");
+            detailview.lines.push(format!(
+                "{}{}: {}",
+                "  ".repeat(d.depth.unwrap_or_default().max(0) as usize),
+                d.topic.as_deref().unwrap_or("?"),
+                mark
+            ));
+        }
+        if !course.descriptor_details.is_empty() {
+            detailview.lines.push(String::new());
+            for d in &course.descriptor_details {
+                detailview.lines.push(format!(
+                    "{} = {}",
+                    d.alpha_key.as_deref().unwrap_or("?"),
+                    d.description.as_deref().unwrap_or_default()
+                ));
+            }
+        }
+    }
+    detailview.scroll = 0;
+}
+
+fn show_thread(detailview: &mut tui::TextBox, discussion: &Discussion) {
+    detailview.title = discussion.title().to_string();
+    detailview.lines = vec![format!("With {}", discussion.participants()), String::new()];
+    for m in &discussion.peer_discussion_messages {
+        detailview.lines.push(format!(
+            "{} ({})",
+            m.person_name.as_deref().unwrap_or("?"),
+            m.date_time
+                .as_deref()
+                .map(parse_datestr)
+                .unwrap_or_default()
+        ));
+        detailview.lines.push(m.text.clone().unwrap_or_default());
+        detailview.lines.push(String::new());
+    }
+    // newest messages are at the bottom
+    detailview.scroll = usize::MAX;
+}
+
+/// schedule times come as "8:05 AM" or "08:05:00" depending on the district
+fn parse_time(time: &str) -> Option<NaiveTime> {
+    ["%I:%M %p", "%I:%M%p", "%I:%M:%S %p", "%H:%M", "%H:%M:%S"]
+        .iter()
+        .find_map(|f| NaiveTime::parse_from_str(time.trim(), f).ok())
+}
+
+/// turns a "/Date(1680000000000)/" timestamp into mm/dd/yy. anything else is passed through
+fn parse_datestr(date: &str) -> String {
+    match datestr_date(date) {
+        Some(dt) => dt.format("%m/%d/%y").to_string(),
+        None => date.to_string(),
+    }
+}
+fn datestr_date(date: &str) -> Option<DateTime<Utc>> {
+    let timestamp: String = date
+        .strip_prefix("/Date(")?
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    let millis = timestamp.parse().ok()?;
+    Some(DateTime::<Utc>::from(
+        UNIX_EPOCH + Duration::from_millis(millis),
+    ))
+}
+
+fn format_score(assignment: &Assignment) -> String {
+    format!(
+        "{}/{}",
+        assignment.score.as_deref().unwrap_or_default(),
+        assignment.max_points.as_deref().unwrap_or_default()
+    )
+}
+
+async fn net_thread(mut rx: Receiver<Command>) {
+    let mut api: Option<SchoolTool> = None;
+    // ends once the tui drops its sender. errors go back to the tui, nothing in here panics
+    while let Some(cmd) = rx.recv().await {
+        match cmd {
+            Command::Login {
+                username,
+                password_hash,
+                baseurl,
+                resp,
+            } => {
+                let result = match SchoolTool::with_hash(baseurl, username, password_hash).await {
+                    Ok(new_api) => {
+                        let students = new_api.get_students().await;
+                        if students.is_ok() {
+                            api = Some(new_api);
+                        }
+                        students
                     }
-                    .to_string();
-                    let _ = resp
-                        .send(QuarterDataResponse {
-                            quarters,
-                            activequarter: dat.get("SelectedMarkingPeriod_ID").unwrap().clone(),
-                            courses: dat.get(fieldname).unwrap().as_array().unwrap().to_vec(),
-                        })
-                        .unwrap();
-                }
-                _ => panic!(),
+                    Err(e) => Err(e),
+                };
+                let _ = resp.send(result);
             }
+            Command::QuarterData {
+                tab,
+                student,
+                building,
+                as_of,
+                quarter,
+                resp,
+            } => {
+                let result = match &api {
+                    Some(api) => view_data(api, tab, &student, building, quarter, as_of).await,
+                    None => Err(ApiError::NotLoggedIn),
+                };
+                let _ = resp.send(result);
+            }
+            Command::OpenDiscussion { id, resp } => {
+                let result = match &api {
+                    Some(api) => open_discussion(api, id).await,
+                    None => Err(ApiError::NotLoggedIn),
+                };
+                let _ = resp.send(result);
+            }
+            Command::DiscussionReply { id, message, resp } => {
+                let result = match &api {
+                    Some(api) => reply(api, id, &message).await,
+                    None => Err(ApiError::NotLoggedIn),
+                };
+                let _ = resp.send(result);
+            }
+            Command::Logout => api = None,
         }
     }
 }
+async fn view_data(
+    api: &SchoolTool,
+    tab: Tab,
+    student: &str,
+    building: Option<i32>,
+    quarter: Option<i32>,
+    as_of: Option<NaiveDate>,
+) -> Result<QuarterDataResponse, ApiError> {
+    Ok(match tab {
+        Tab::Notifications => QuarterDataResponse {
+            quarters: vec![],
+            activequarter: None,
+            data: ViewData::Notifications(api.notifications(student).await?),
+        },
+        Tab::Discussions => QuarterDataResponse {
+            quarters: vec![],
+            activequarter: None,
+            data: ViewData::Discussions(api.discussions().await?),
+        },
+        Tab::Schedule => {
+            let dat = api.schedule(student, building, quarter, as_of).await?;
+            QuarterDataResponse {
+                quarters: dat.semesters.clone(),
+                activequarter: dat.selected_semester,
+                data: ViewData::Schedule(dat),
+            }
+        }
+        Tab::Attendance => {
+            let dat = api.attendance(student, building, quarter, as_of).await?;
+            let quarters = api
+                .attendance_marking_periods(&dat, student, building, as_of)
+                .await?;
+            QuarterDataResponse {
+                quarters,
+                activequarter: dat.selected_marking_period,
+                data: ViewData::Attendance(dat),
+            }
+        }
+        Tab::Assignments | Tab::Grades => {
+            let dat = api
+                .quarter_data(tab.name(), student, building, quarter, as_of)
+                .await?;
+            QuarterDataResponse {
+                quarters: dat.marking_periods,
+                activequarter: dat.selected_marking_period,
+                data: if tab == Tab::Grades {
+                    ViewData::Grades(dat.courses)
+                } else {
+                    ViewData::Assignments(dat.courses)
+                },
+            }
+        }
+    })
+}
+async fn open_discussion(api: &SchoolTool, id: i32) -> Result<Discussion, ApiError> {
+    let discussion = api.discussion(id).await?;
+    api.discussion_read(id).await?;
+    Ok(discussion)
+}
+async fn reply(api: &SchoolTool, id: i32, message: &str) -> Result<Discussion, ApiError> {
+    let discussion = api.discussion(id).await?;
+    let to = discussion
+        .recipient(api.person_guid())
+        .ok_or(ApiError::NoRecipient)?
+        .to_string();
+    api.discussion_reply(id, &to, message).await?;
+    api.discussion_read(id).await?;
+    api.discussion(id).await
+}
@@ -1,147 +1,966 @@
-use reqwest::{Client, ClientBuilder, Method};
-use serde_json::json;
-use serde_json::Value;
+// the models mirror what the server sends, not all of it is displayed. fields that are only kept
+// for completeness get their own #[allow(dead_code)]
+
+use base64::Engine;
+use chrono::{Local, NaiveDate, TimeZone};
+use reqwest::{Client, ClientBuilder, Method, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer};
+use serde_json::{json, Value};
 use std::error::Error;
 use std::fmt::Display;
+use std::sync::Mutex;
+use std::time::Duration;
 
 pub const ENDPOINT: &str = "/WebAPI.mvc/Mobile/SchoolToolMobile";
 #[derive(Debug)]
 pub struct SchoolTool {
-    auth_header: String,
+    /// swapped out whenever the session gets renewed
+    auth_header: Mutex<String>,
     client: Client,
     base_url: String,
     guid: String,
+    username: String,
+    password_hash: String,
 }
 #[derive(Debug, Clone)]
 pub struct Student {
     pub name: String,
     pub guid: String,
-    pub cycle_day: Option<u8>,
+    pub buildings: Vec<Building>,
+}
+/// a school the student is enrolled in. students enrolled in two schools have one per school
+#[derive(Debug, Clone)]
+pub struct Building {
+    pub id: i32,
+    pub name: String,
+    pub cycle_day: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct LoginResponse {
+    pub key: Option<String>,
+    pub person_guid: Option<String>,
+    pub logged_in: Option<bool>,
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct HomeResponse {
+    #[allow(dead_code)]
+    pub first_name: Option<String>,
+    #[allow(dead_code)]
+    pub middle_name: Option<String>,
+    #[allow(dead_code)]
+    pub last_name: Option<String>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub students: Vec<StudentRecord>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct StudentsData {
+    #[serde(alias = "StudentRecords", default, deserialize_with = "nullable")]
+    students: Vec<StudentRecord>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct StudentRecord {
+    pub first_name: Option<String>,
+    pub middle_name: Option<String>,
+    pub last_name: Option<String>,
+    pub student_person_guid: String,
+    #[allow(dead_code)]
+    #[serde(rename = "StudentID")]
+    pub student_id: Option<String>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub student_building_school_level_cycle_days: Vec<StudentBuildingSchoolLevelCycleDay>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct StudentBuildingSchoolLevelCycleDay {
+    pub building_school_level_text: Option<String>,
+    #[serde(rename = "Building_SchoolLevel_ID")]
+    pub building_school_level_id: Option<i32>,
+    pub cycle_day: Option<String>,
+}
+
+/// response of both the Assignments and Grades endpoints
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct QuarterData {
+    #[serde(rename = "SelectedMarkingPeriod_ID")]
+    pub selected_marking_period: Option<i32>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub marking_periods: Vec<MarkingPeriod>,
+    #[serde(
+        rename = "AssignmentCourses",
+        alias = "GradeCourses",
+        default,
+        deserialize_with = "nullable"
+    )]
+    pub courses: Vec<Course>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct AttendanceData {
+    #[serde(rename = "SelectedMarkingPeriod_ID")]
+    pub selected_marking_period: Option<i32>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub marking_periods: Vec<MarkingPeriod>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub daily_attendance: DailyAttendanceRecord,
+    #[serde(default, deserialize_with = "nullable")]
+    pub attendance_courses: Vec<AttendanceCourse>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DailyAttendanceRecord {
+    pub absent_count: Option<i32>,
+    pub arrived_late_arrival_count: Option<i32>,
+    pub left_early_dismissal_count: Option<i32>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub absence_list: Vec<DailyAttendance>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DailyAttendance {
+    /// absent, arrived late, left early...
+    pub record_type: Option<String>,
+    pub date_absent: Option<String>,
+    #[allow(dead_code)]
+    pub date_time_absent: Option<String>,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct AttendanceCourse {
+    pub course_name: Option<String>,
+    pub period: Option<String>,
+    pub absent_count: Option<i32>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub absence_list: Vec<Absence>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Absence {
+    pub date_absent: Option<String>,
+    #[allow(dead_code)]
+    pub date_time_absent: Option<String>,
+    pub reason: Option<String>,
+    pub reason_text: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ScheduleData {
+    #[serde(rename = "SelectedSemester_ID")]
+    pub selected_semester: Option<i32>,
+    /// semesters have the same shape as marking periods
+    #[serde(default, deserialize_with = "nullable")]
+    pub semesters: Vec<MarkingPeriod>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub cycle_days: Vec<String>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub schedule_courses: Vec<ScheduleCourse>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ScheduleCourse {
+    #[allow(dead_code)]
+    #[serde(rename = "CourseID")]
+    pub course_id: Option<String>,
+    pub course_name: Option<String>,
+    pub start_time: Option<String>,
+    pub end_time: Option<String>,
+    pub period: Option<String>,
+    pub last_period: Option<String>,
+    pub room: Option<String>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub offered_cycle_days: Vec<String>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub faculty: Vec<Faculty>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DiscussionsData {
+    #[serde(default, deserialize_with = "nullable")]
+    peer_discussions: Vec<Discussion>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Discussion {
+    #[serde(rename = "ID")]
+    pub id: i32,
+    pub title: Option<String>,
+    pub last_message: Option<String>,
+    #[allow(dead_code)]
+    pub date_created: Option<String>,
+    /// unread messages
+    pub notification_count: Option<i32>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub peer_discussion_members: Vec<DiscussionMember>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub peer_discussion_messages: Vec<DiscussionMessage>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DiscussionMember {
+    pub person_guid: Option<String>,
+    pub person_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DiscussionMessage {
+    pub date_time: Option<String>,
+    pub text: Option<String>,
+    pub person_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct NotificationsData {
+    #[serde(default, deserialize_with = "nullable")]
+    notifications: Vec<Notification>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Notification {
+    pub notification_date: Option<String>,
+    pub subject: Option<String>,
+    pub from: Option<String>,
+    #[allow(dead_code)]
+    pub student_name: Option<String>,
+    pub message: Option<String>,
+    #[allow(dead_code)]
+    pub notification_type: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct MarkingPeriod {
+    pub id: Option<i32>,
+    pub name: Option<String>,
+    pub is_current: Option<bool>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Course {
+    #[serde(rename = "CourseID")]
+    pub course_id: Option<String>,
+    pub course_name: Option<String>,
+    pub period: Option<String>,
+    #[allow(dead_code)]
+    pub room: Option<String>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub faculty: Vec<Faculty>,
+    pub grade_book_average: Option<String>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub assignments: Vec<Assignment>,
+    pub traditional_grade: Option<TraditionalGrade>,
+    /// standards based grading, schools that don't use it leave these out
+    pub has_descriptor_grade: Option<bool>,
+    #[serde(default, deserialize_with = "lenient")]
+    pub descriptor_grade: Option<DescriptorGrade>,
+    #[serde(default, deserialize_with = "lenient")]
+    pub descriptor_details: Vec<DescriptorDetail>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DescriptorGrade {
+    #[serde(default, deserialize_with = "nullable")]
+    pub descriptors: Vec<CourseTopicGrade>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct CourseTopicGrade {
+    pub topic: Option<String>,
+    #[serde(default, deserialize_with = "loose_text")]
+    pub score: Option<String>,
+    pub alpha_key: Option<String>,
+    /// how far it's nested under the topics above it
+    pub depth: Option<i32>,
+}
+
+/// what each descriptor key means, like "3 - Meets the standard"
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DescriptorDetail {
+    pub alpha_key: Option<String>,
+    #[serde(alias = "Descriptor", alias = "Name")]
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Assignment {
+    pub assignment_name: Option<String>,
+    pub assignment_date: Option<String>,
+    pub score: Option<String>,
+    pub max_points: Option<String>,
+    pub attributes: Option<String>,
+    pub notes: Option<String>,
+    /// the description teachers write up, only some schools send it
+    pub assignment_text: Option<String>,
+    #[serde(default, alias = "CategoryName", deserialize_with = "loose_text")]
+    pub category: Option<String>,
+    #[serde(default, deserialize_with = "loose_text")]
+    pub weight: Option<String>,
+    #[serde(default, deserialize_with = "loose_text")]
+    pub comments: Option<String>,
+    pub is_graded: Option<bool>,
+    pub is_due_today: Option<bool>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct TraditionalGrade {
+    pub grade: Option<String>,
+    pub grade_book_average: Option<String>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub comments: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Faculty {
+    pub salutation: Option<String>,
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+    pub email: Option<String>,
+}
+
+// the server likes to send `null` for empty lists
+fn nullable<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + Default,
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+// for the parts that aren't documented anywhere. if they don't look like we expect they're dropped
+// instead of failing the whole response
+fn lenient<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned + Default,
+{
+    Ok(serde_json::from_value(Value::deserialize(deserializer)?).unwrap_or_default())
+}
+
+// fields that show up as a string, a number or a list depending on the school
+fn loose_text<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(match Value::deserialize(deserializer)? {
+        Value::Null => None,
+        Value::String(s) => Some(s),
+        Value::Array(items) => Some(
+            items
+                .iter()
+                .map(|i| {
+                    i.as_str()
+                        .map(String::from)
+                        .unwrap_or_else(|| i.to_string())
+                })
+                .collect::<Vec<_>>()
+                .join(" | "),
+        ),
+        other => Some(other.to_string()),
+    }
+    .filter(|s| !s.trim().is_empty()))
+}
+
+impl StudentRecord {
+    pub fn full_name(&self) -> String {
+        [&self.first_name, &self.middle_name, &self.last_name]
+            .into_iter()
+            .flatten()
+            .filter(|s| !s.is_empty())
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+impl From<StudentRecord> for Student {
+    fn from(student: StudentRecord) -> Self {
+        let mut buildings: Vec<Building> = vec![];
+        for record in &student.student_building_school_level_cycle_days {
+            let Some(id) = record.building_school_level_id else {
+                continue;
+            };
+            if buildings.iter().any(|b| b.id == id) {
+                continue;
+            }
+            buildings.push(Building {
+                id,
+                name: record
+                    .building_school_level_text
+                    .clone()
+                    .filter(|n| !n.is_empty())
+                    .unwrap_or_else(|| format!("Building {}", id)),
+                cycle_day: record.cycle_day.clone().filter(|c| !c.is_empty()),
+            });
+        }
+        Student {
+            name: student.full_name(),
+            guid: student.student_person_guid,
+            buildings,
+        }
+    }
+}
+
+impl MarkingPeriod {
+    /// the "Final" entry isn't a real marking period, it only means something for grades
+    pub fn is_final(&self) -> bool {
+        self.name
+            .as_deref()
+            .is_some_and(|n| n.trim().eq_ignore_ascii_case("final"))
+    }
+}
+
+impl Course {
+    pub fn name(&self) -> &str {
+        self.course_name.as_deref().unwrap_or("Unknown course")
+    }
+    pub fn descriptors(&self) -> &[CourseTopicGrade] {
+        match (&self.descriptor_grade, self.has_descriptor_grade) {
+            (Some(d), Some(true)) => &d.descriptors,
+            _ => &[],
+        }
+    }
+}
+
+impl Discussion {
+    pub fn title(&self) -> &str {
+        self.title.as_deref().unwrap_or("(no title)")
+    }
+    pub fn is_unread(&self) -> bool {
+        self.notification_count.unwrap_or_default() > 0
+    }
+    pub fn participants(&self) -> String {
+        self.peer_discussion_members
+            .iter()
+            .filter_map(|m| m.person_name.clone())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+    /// who a reply from `me` goes to
+    pub fn recipient(&self, me: &str) -> Option<&str> {
+        self.peer_discussion_members
+            .iter()
+            .filter_map(|m| m.person_guid.as_deref())
+            .find(|g| !g.eq_ignore_ascii_case(me))
+    }
+}
+
+impl Notification {
+    /// the server doesn't give notifications an id, so this stands in for one
+    pub fn key(&self) -> String {
+        format!(
+            "{}|{}|{}",
+            self.notification_date.as_deref().unwrap_or_default(),
+            self.from.as_deref().unwrap_or_default(),
+            self.subject.as_deref().unwrap_or_default()
+        )
+    }
+}
+
+impl ScheduleData {
+    /// the rotation, falling back to whatever days the courses say they meet on
+    pub fn cycle_days(&self) -> Vec<String> {
+        if !self.cycle_days.is_empty() {
+            return self.cycle_days.clone();
+        }
+        let mut days: Vec<String> = vec![];
+        for day in self
+            .schedule_courses
+            .iter()
+            .flat_map(|c| &c.offered_cycle_days)
+        {
+            if !days.contains(day) {
+                days.push(day.clone());
+            }
+        }
+        days.sort();
+        days
+    }
+}
+
+impl ScheduleCourse {
+    pub fn name(&self) -> &str {
+        self.course_name.as_deref().unwrap_or("Unknown course")
+    }
+    /// courses that don't list any cycle days meet every day
+    pub fn meets_on(&self, cycle_day: &str) -> bool {
+        self.offered_cycle_days.is_empty()
+            || self
+                .offered_cycle_days
+                .iter()
+                .any(|d| same_cycle_day(d, cycle_day))
+    }
 }
+
+impl Faculty {
+    pub fn name(&self) -> String {
+        [&self.salutation, &self.first_name, &self.last_name]
+            .into_iter()
+            .flatten()
+            .filter(|s| !s.is_empty())
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// cycle days show up as both "2" and "Day 2" depending on the endpoint
+pub fn same_cycle_day(a: &str, b: &str) -> bool {
+    fn normalize(s: &str) -> String {
+        let s = s.trim().to_lowercase();
+        s.strip_prefix("day").unwrap_or(&s).trim().to_string()
+    }
+    normalize(a) == normalize(b)
+}
+
+impl DailyAttendanceRecord {
+    fn count_of(&self, record_type: &str) -> i32 {
+        self.absence_list
+            .iter()
+            .filter(|a| {
+                a.record_type
+                    .as_deref()
+                    .unwrap_or_default()
+                    .to_lowercase()
+                    .contains(record_type)
+            })
+            .count() as i32
+    }
+    // older servers leave the counts out, so fall back to counting the records
+    pub fn absent(&self) -> i32 {
+        self.absent_count.unwrap_or_else(|| self.count_of("absent"))
+    }
+    pub fn late(&self) -> i32 {
+        self.arrived_late_arrival_count
+            .unwrap_or_else(|| self.count_of("late"))
+    }
+    pub fn left_early(&self) -> i32 {
+        self.left_early_dismissal_count
+            .unwrap_or_else(|| self.count_of("early"))
+    }
+}
+
+impl AttendanceCourse {
+    pub fn name(&self) -> &str {
+        self.course_name.as_deref().unwrap_or("Unknown course")
+    }
+    pub fn absent(&self) -> i32 {
+        self.absent_count.unwrap_or(self.absence_list.len() as i32)
+    }
+}
+
 #[derive(Debug)]
-struct DataError {}
-impl Error for DataError {}
-impl Display for DataError {
+pub enum ApiError {
+    /// the server turned down the username/password, with its reason if it gave one
+    BadCredentials(Option<String>),
+    /// the server answered with html (or nothing json-shaped), so the base url is probably wrong
+    WrongBaseUrl,
+    Network(reqwest::Error),
+    /// the auth header isn't accepted anymore
+    SessionExpired,
+    /// something was asked for before logging in
+    NotLoggedIn,
+    /// a reply to a discussion that only has us in it
+    NoRecipient,
+    /// the server has nothing at this endpoint
+    NotFound(String),
+    /// the user stopped waiting for an answer
+    Cancelled,
+    /// the json didn't look like what we expected
+    Schema(serde_json::Error),
+}
+impl Error for ApiError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ApiError::Network(e) => Some(e),
+            ApiError::Schema(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+impl Display for ApiError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "error parsing data.").unwrap();
-        Ok(())
+        match self {
+            ApiError::BadCredentials(Some(msg)) if !msg.is_empty() => {
+                write!(f, "login failed: {}", msg)
+            }
+            ApiError::BadCredentials(_) => write!(f, "incorrect username or password"),
+            ApiError::WrongBaseUrl => write!(
+                f,
+                "that doesn't look like a SchoolTool server, check the base url"
+            ),
+            ApiError::Network(e) => write!(f, "couldn't reach the server: {}", e),
+            ApiError::SessionExpired => write!(f, "your session expired, log in again"),
+            ApiError::NotLoggedIn => write!(f, "not logged in yet"),
+            ApiError::NoRecipient => {
+                write!(f, "there's nobody else in this discussion to reply to")
+            }
+            ApiError::Cancelled => write!(f, "stopped waiting for the server"),
+            ApiError::NotFound(endpoint) => write!(f, "the server doesn't have {} (404)", endpoint),
+            ApiError::Schema(e) => write!(f, "couldn't understand the server's response: {}", e),
+        }
+    }
+}
+impl From<reqwest::Error> for ApiError {
+    fn from(e: reqwest::Error) -> Self {
+        ApiError::Network(e)
+    }
+}
+impl From<serde_json::Error> for ApiError {
+    fn from(e: serde_json::Error) -> Self {
+        ApiError::Schema(e)
     }
 }
 
 impl SchoolTool {
-    pub async fn new(
+    /// logs in with what `encode_password` made, so the password itself never has to be kept
+    pub async fn with_hash(
         base_url: String,
         username: String,
-        password: String,
-    ) -> Result<Self, Box<dyn Error>> {
-        let client = ClientBuilder::new().user_agent("Mozilla/5.0 (X11; CrOS x86_64 14695.142.0) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/102.0.0.0 Safari/537.36")
+        password_hash: String,
+    ) -> Result<Self, ApiError> {
+        // a server that stalls shows up as a network error instead of loading forever
+        let client = ClientBuilder::new().timeout(Duration::from_secs(30)).user_agent("Mozilla/5.0 (X11; CrOS x86_64 14695.142.0) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/102.0.0.0 Safari/537.36")
         .build()
         .unwrap();
 
-        let password_hash = encode_password(password);
-
-        let login_struct = &log_in(&client, &username, &password_hash, &base_url).await?;
-        let guid_blob = login_struct.get("PersonGuid").unwrap().as_str().unwrap();
-        let auth_header = format!(
-            "{} {}:{}",
-            login_struct
-                .get("Key")
-                .ok_or(DataError {})?
-                .as_str()
-                .unwrap(),
-            guid_blob,
-            &password_hash
-        );
-
-        let guid = guid_blob.split("|").nth(1).ok_or(DataError {})?.to_string();
+        let (auth_header, guid) =
+            authenticate(&client, &base_url, &username, &password_hash).await?;
         Ok(Self {
             base_url,
-            auth_header,
+            auth_header: Mutex::new(auth_header),
             client,
             guid,
+            username,
+            password_hash,
         })
     }
-    pub async fn get_student(&self, student_guid: Option<&str>) -> Result<Student, Box<dyn Error>> {
-        let homereq = self
-            .client
-            .request(Method::POST, format!("{}{}/Home", self.base_url, ENDPOINT))
-            .header("Content-Type", "application/json;charset=utf-8")
-            .header("authorization", &self.auth_header)
-            .body("\"\"");
-        let body = homereq.send().await?.text().await?;
-        let home_struct: Value = serde_json::from_str(&body)?;
-        let student_struct = home_struct
-            .get("Students")
-            .unwrap()
-            .as_array()
-            .unwrap()
-            .iter()
-            .find(|v| {
-                v.get("StudentPersonGuid").unwrap().as_str().unwrap()
-                    == student_guid.unwrap_or(&self.guid)
-            })
-            .unwrap();
-
-        let cycle_day = student_struct
-            .get("StudentBuildingSchoolLevelCycleDays")
-            .unwrap()
-            .as_array()
-            .unwrap()[0]
-            .get("CycleDay")
-            .unwrap()
-            .as_str()
-            .unwrap();
-        let name = format!(
-            "{} {} {}",
-            student_struct.get("FirstName").unwrap().as_str().unwrap(),
-            student_struct.get("MiddleName").unwrap().as_str().unwrap(),
-            student_struct.get("LastName").unwrap().as_str().unwrap()
-        );
-        Ok(Student {
-            guid: student_struct
-                .get("StudentPersonGuid")
-                .unwrap()
-                .as_str()
-                .unwrap()
-                .to_string(),
-            name,
-            cycle_day: u8::from_str_radix(cycle_day, 10).ok(),
-        })
+    /// logs in again with the same credentials, for when the server forgets about us
+    async fn renew(&self) -> Result<(), ApiError> {
+        let (auth_header, _) = authenticate(
+            &self.client,
+            &self.base_url,
+            &self.username,
+            &self.password_hash,
+        )
+        .await?;
+        *self.auth_header.lock().unwrap() = auth_header;
+        Ok(())
     }
-    pub async fn quarter_data(
-        &self,
-        data_type: String,
-        guid: String,
-        quarter: Value,
-    ) -> Result<Value, Box<dyn Error>> {
-        let g = self
+    async fn send(&self, endpoint: &str, body: String) -> Result<Response, ApiError> {
+        let auth_header = self.auth_header.lock().unwrap().clone();
+        let req = self
             .client
             .request(
                 Method::POST,
-                format!("{}{}/{}", self.base_url, ENDPOINT, data_type),
+                format!("{}{}/{}", self.base_url, ENDPOINT, endpoint),
             )
             .header("Content-Type", "application/json;charset=utf-8")
-            .header("authorization", &self.auth_header)
-            .body(
-                json!({
-                    "studentGuid":guid,
-                    "buildingSchoolLevelId":4,
-                    "markingPeriodId":quarter,
-                    "asOfDate":null,
-                })
-                .to_string(),
-            );
-
-        let raw = g.send().await?.text().await?;
-        Ok(serde_json::from_str(&raw)?)
+            .header("authorization", auth_header)
+            .body(body);
+        Ok(req.send().await?)
+    }
+    // an expired session gets renewed and the request tried once more
+    async fn post<T: DeserializeOwned>(&self, endpoint: &str, body: String) -> Result<T, ApiError> {
+        match self.post_once(endpoint, body.clone()).await {
+            Err(ApiError::SessionExpired) => {
+                self.renew().await?;
+                self.post_once(endpoint, body).await
+            }
+            r => r,
+        }
+    }
+    async fn post_once<T: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        body: String,
+    ) -> Result<T, ApiError> {
+        let resp = self.send(endpoint, body).await?;
+        check_status(&resp, endpoint)?;
+        match parse_response(resp).await {
+            Err(ApiError::WrongBaseUrl) | Err(ApiError::BadCredentials(_)) => {
+                // we already logged in with this url, so html here is the login page
+                Err(ApiError::SessionExpired)
+            }
+            r => r,
+        }
+    }
+    /// for endpoints that don't answer with anything useful
+    async fn post_ignored(&self, endpoint: &str, body: String) -> Result<(), ApiError> {
+        match self.post_ignored_once(endpoint, body.clone()).await {
+            Err(ApiError::SessionExpired) => {
+                self.renew().await?;
+                self.post_ignored_once(endpoint, body).await
+            }
+            r => r,
+        }
+    }
+    async fn post_ignored_once(&self, endpoint: &str, body: String) -> Result<(), ApiError> {
+        let resp = self.send(endpoint, body).await?;
+        check_status(&resp, endpoint)?;
+        let status = resp.status();
+        if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN {
+            return Err(ApiError::SessionExpired);
+        }
+        let body = resp.error_for_status()?.text().await?;
+        if body.trim_start().starts_with('<') {
+            // redirected to the login page
+            return Err(ApiError::SessionExpired);
+        }
+        Ok(())
+    }
+    /// the logged in person, which isn't the student on parent accounts
+    pub fn person_guid(&self) -> &str {
+        &self.guid
+    }
+    pub async fn home(&self) -> Result<HomeResponse, ApiError> {
+        self.post("Home", "\"\"".into()).await
+    }
+    /// the /Students endpoint, which is how parent accounts list their kids
+    pub async fn students(&self) -> Result<Vec<StudentRecord>, ApiError> {
+        let data: StudentsData = self.post("Students", "\"\"".into()).await?;
+        Ok(data.students)
     }
+    /// everyone this account can look at, the logged in student first
+    pub async fn get_students(&self) -> Result<Vec<Student>, ApiError> {
+        let mut records = self.home().await?.students;
+        match self.students().await {
+            Ok(more) => {
+                for r in more {
+                    if !records.iter().any(|s| {
+                        s.student_person_guid
+                            .eq_ignore_ascii_case(&r.student_person_guid)
+                    }) {
+                        records.push(r);
+                    }
+                }
+            }
+            Err(e @ ApiError::Network(_)) => return Err(e),
+            // student accounts aren't allowed to use it, which can look like anything from a 403
+            // to an expired session
+            Err(_) => (),
+        }
+        records.sort_by_key(|s| !s.student_person_guid.eq_ignore_ascii_case(&self.guid));
+        if records.is_empty() {
+            return Err(schema_error("there are no students on this account"));
+        }
+        Ok(records.into_iter().map(Student::from).collect())
+    }
+    pub async fn quarter_data(
+        &self,
+        data_type: &str,
+        guid: &str,
+        building: Option<i32>,
+        quarter: Option<i32>,
+        as_of: Option<NaiveDate>,
+    ) -> Result<QuarterData, ApiError> {
+        self.post(data_type, quarter_body(guid, building, quarter, as_of))
+            .await
+    }
+    pub async fn schedule(
+        &self,
+        guid: &str,
+        building: Option<i32>,
+        semester: Option<i32>,
+        as_of: Option<NaiveDate>,
+    ) -> Result<ScheduleData, ApiError> {
+        self.post(
+            "Schedule",
+            json!({
+                "studentGuid":guid,
+                "buildingSchoolLevelId":building,
+                "semesterId":semester,
+                "asOfDate":as_of_date(as_of),
+            })
+            .to_string(),
+        )
+        .await
+    }
+    pub async fn notifications(&self, guid: &str) -> Result<Vec<Notification>, ApiError> {
+        let data: NotificationsData = self
+            .post("Notifications", json!({ "studentGuid": guid }).to_string())
+            .await?;
+        Ok(data.notifications)
+    }
+    pub async fn discussions(&self) -> Result<Vec<Discussion>, ApiError> {
+        let data: DiscussionsData = self.post("Discussions", "\"\"".into()).await?;
+        Ok(data.peer_discussions)
+    }
+    /// a single discussion, with its messages
+    pub async fn discussion(&self, discussion_id: i32) -> Result<Discussion, ApiError> {
+        self.post(
+            "Discussion",
+            json!({ "discussionId": discussion_id }).to_string(),
+        )
+        .await
+    }
+    pub async fn discussion_read(&self, discussion_id: i32) -> Result<(), ApiError> {
+        self.post_ignored(
+            "DiscussionRead",
+            json!({ "discussionId": discussion_id }).to_string(),
+        )
+        .await
+    }
+    pub async fn discussion_reply(
+        &self,
+        discussion_id: i32,
+        to_person_guid: &str,
+        message: &str,
+    ) -> Result<(), ApiError> {
+        self.post_ignored(
+            "DiscussionReply",
+            json!({
+                "toPersonGuid": to_person_guid,
+                "message": message,
+                "discussionId": discussion_id,
+            })
+            .to_string(),
+        )
+        .await
+    }
+    pub async fn attendance(
+        &self,
+        guid: &str,
+        building: Option<i32>,
+        quarter: Option<i32>,
+        as_of: Option<NaiveDate>,
+    ) -> Result<AttendanceData, ApiError> {
+        self.post("Attendance", quarter_body(guid, building, quarter, as_of))
+            .await
+    }
+    /// attendance doesn't always send its own marking periods, the assignments ones match
+    pub async fn attendance_marking_periods(
+        &self,
+        data: &AttendanceData,
+        guid: &str,
+        building: Option<i32>,
+        as_of: Option<NaiveDate>,
+    ) -> Result<Vec<MarkingPeriod>, ApiError> {
+        if !data.marking_periods.is_empty() {
+            return Ok(data.marking_periods.clone());
+        }
+        Ok(self
+            .quarter_data("Assignments", guid, building, None, as_of)
+            .await?
+            .marking_periods)
+    }
+}
+// once logged in, these aren't the session's fault so there's no point in renewing it
+fn check_status(resp: &Response, endpoint: &str) -> Result<(), ApiError> {
+    if resp.status() == StatusCode::NOT_FOUND {
+        return Err(ApiError::NotFound(endpoint.into()));
+    }
+    if resp.status().is_server_error() {
+        resp.error_for_status_ref()?;
+    }
+    Ok(())
+}
+// a 401 or an empty body means the server refused us. at login that's bad credentials,
+// anywhere else `post` turns it into an expired session
+async fn parse_response<T: DeserializeOwned>(resp: Response) -> Result<T, ApiError> {
+    let status = resp.status();
+    if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN {
+        return Err(ApiError::BadCredentials(None));
+    }
+    let body = resp.text().await?;
+    let body = body.trim_start();
+    if body.starts_with('<') || status == StatusCode::NOT_FOUND {
+        return Err(ApiError::WrongBaseUrl);
+    }
+    if body.is_empty() || body == "\"\"" || body == "null" {
+        return Err(ApiError::BadCredentials(None));
+    }
+    Ok(serde_json::from_str(body)?)
+}
+fn quarter_body(
+    guid: &str,
+    building: Option<i32>,
+    quarter: Option<i32>,
+    as_of: Option<NaiveDate>,
+) -> String {
+    json!({
+        "studentGuid":guid,
+        "buildingSchoolLevelId":building,
+        "markingPeriodId":quarter,
+        "asOfDate":as_of_date(as_of),
+    })
+    .to_string()
+}
+// the server wants dates the same way it sends them, null means today
+fn as_of_date(date: Option<NaiveDate>) -> Value {
+    let Some(date) = date else {
+        return Value::Null;
+    };
+    let midnight = date.and_hms_opt(0, 0, 0).unwrap();
+    let ms = Local
+        .from_local_datetime(&midnight)
+        .earliest()
+        .map(|d| d.timestamp_millis())
+        .unwrap_or_else(|| midnight.timestamp_millis());
+    Value::String(format!("/Date({})/", ms))
+}
+fn schema_error(msg: &str) -> ApiError {
+    ApiError::Schema(serde::de::Error::custom(msg))
+}
+/// returns the authorization header and the person guid
+async fn authenticate(
+    client: &Client,
+    base_url: &str,
+    username: &str,
+    password_hash: &str,
+) -> Result<(String, String), ApiError> {
+    let login = log_in(client, username, password_hash, base_url).await?;
+    let (Some(key), Some(guid_blob)) = (login.key, login.person_guid) else {
+        return Err(ApiError::BadCredentials(login.message));
+    };
+    if login.logged_in == Some(false) {
+        return Err(ApiError::BadCredentials(login.message));
+    }
+    let auth_header = format!("{} {}:{}", key, guid_blob, password_hash);
+
+    let guid = guid_blob
+        .split('|')
+        .nth(1)
+        .ok_or_else(|| schema_error("PersonGuid is missing the person id"))?
+        .to_string();
+    Ok((auth_header, guid))
 }
 async fn log_in(
     client: &Client,
     username: &str,
     password_hash: &str,
     base_url: &str,
-) -> Result<Value, Box<dyn Error>> {
+) -> Result<LoginResponse, ApiError> {
     let req = client
         .request(Method::POST, format!("{}{}/AppLogin", base_url, ENDPOINT))
         .header("Content-Type", "application/json;charset=utf-8")
@@ -152,9 +971,9 @@ async fn log_in(
             })
             .to_string(),
         );
-    Ok(serde_json::from_str(&req.send().await?.text().await?)?)
+    parse_response(req.send().await?).await
 }
-fn encode_password(password: String) -> String {
+pub fn encode_password(password: String) -> String {
     let passlen = password.chars().count();
 
     let salt = "tAYOdhqzEERgIbU8WGdH2EI6YS77pILeLVsOjVd5gzVvX43Blm";
@@ -169,12 +988,14 @@ fn encode_password(password: String) -> String {
     for ch in buffer3.chars() {
         buffer4 += &format!("{:x}{}", ch as u8, salt2);
     }
-    base64::encode(buffer4)
+    base64::engine::general_purpose::STANDARD.encode(buffer4)
 }
 
 // this is never used. just for shits and giggles and a bit of code golfing.
 fn _decode_password(encoded: String) -> String {
-    let bytes = &base64::decode(encoded).unwrap();
+    let bytes = &base64::engine::general_purpose::STANDARD
+        .decode(encoded)
+        .unwrap();
     let s = std::str::from_utf8(bytes).unwrap();
     (0..s.len())
         .step_by(12)
@@ -182,3 +1003,61 @@ fn _decode_password(encoded: String) -> String {
         .rev()
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nulls_become_empty() {
+        let data: QuarterData = serde_json::from_value(json!({
+            "SelectedMarkingPeriod_ID": null,
+            "MarkingPeriods": null,
+            "AssignmentCourses": [
+                {
+                    "CourseName": "Math",
+                    "Faculty": null,
+                    "Assignments": null,
+                    "TraditionalGrade": null,
+                },
+                {
+                    "CourseName": "Art",
+                    "TraditionalGrade": { "Grade": "A", "Comments": null },
+                    "Assignments": [{ "AssignmentName": "Essay", "Comments": null }],
+                },
+            ],
+        }))
+        .unwrap();
+        assert!(data.marking_periods.is_empty());
+        let [math, art] = &data.courses[..] else {
+            panic!("expected two courses, got {:?}", data.courses);
+        };
+        assert!(math.faculty.is_empty());
+        assert!(math.assignments.is_empty());
+        assert!(math.traditional_grade.is_none());
+        let grade = art.traditional_grade.as_ref().unwrap();
+        assert_eq!(grade.grade.as_deref(), Some("A"));
+        assert!(grade.comments.is_empty());
+        assert_eq!(art.assignments[0].comments, None);
+    }
+
+    #[test]
+    fn loose_fields() {
+        let course: Course = serde_json::from_value(json!({
+            "CourseName": "Art",
+            "Assignments": [
+                { "Category": 3, "Weight": [1, "two"], "Comments": "  " },
+                { "CategoryName": "Homework", "Weight": 0.5 },
+            ],
+            "DescriptorGrade": "not what we expected",
+        }))
+        .unwrap();
+        let a = &course.assignments;
+        assert_eq!(a[0].category.as_deref(), Some("3"));
+        assert_eq!(a[0].weight.as_deref(), Some("1 | two"));
+        assert_eq!(a[0].comments, None);
+        assert_eq!(a[1].category.as_deref(), Some("Homework"));
+        assert_eq!(a[1].weight.as_deref(), Some("0.5"));
+        assert!(course.descriptor_grade.is_none());
+    }
+}
@@ -0,0 +1,220 @@
+// secrets live in the os keyring. when there isn't one (no secret service running, headless
+// boxes) they go in an encrypted file next to the config instead. set SCHOOLTERM_SECRET_STORE=file
+// to always use the file.
+// what gets stored is the password hash sealed with the user's passphrase, so neither the keyring
+// nor the file is enough on its own
+use argon2::Argon2;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const SERVICE: &str = "schoolterm";
+const NONCE_LEN: usize = 12;
+const SALT_LEN: usize = 16;
+const SEALED_PREFIX: &str = "sealed:";
+
+pub struct SecretStore {
+    file: PathBuf,
+    key_file: PathBuf,
+    use_keyring: bool,
+}
+impl SecretStore {
+    pub fn new(config_dir: &Path) -> Self {
+        SecretStore {
+            file: config_dir.join("schoolterm-secrets"),
+            key_file: config_dir.join("schoolterm-key"),
+            use_keyring: std::env::var("SCHOOLTERM_SECRET_STORE").as_deref() != Ok("file"),
+        }
+    }
+    /// secrets are stored per login, so switching accounts doesn't clobber anything
+    pub fn account(username: &str, baseurl: &str) -> String {
+        format!("{}@{}", username, baseurl)
+    }
+    pub fn get(&self, account: &str) -> Option<String> {
+        if self.use_keyring {
+            if let Ok(secret) = keyring::Entry::new(SERVICE, account).and_then(|e| e.get_password())
+            {
+                return Some(secret);
+            }
+        }
+        let secrets = self.read_file().ok()?;
+        let blob = STANDARD.decode(secrets.get(account)?).ok()?;
+        if blob.len() < NONCE_LEN {
+            return None;
+        }
+        let (nonce, ciphertext) = blob.split_at(NONCE_LEN);
+        let plaintext = self
+            .cipher()
+            .ok()?
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .ok()?;
+        String::from_utf8(plaintext).ok()
+    }
+    pub fn set(&self, account: &str, secret: &str) -> Result<(), Box<dyn Error>> {
+        if self.use_keyring
+            && keyring::Entry::new(SERVICE, account)
+                .and_then(|e| e.set_password(secret))
+                .is_ok()
+        {
+            // don't leave an older copy lying around in the file
+            return self.remove_from_file(account);
+        }
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher()?
+            .encrypt(&nonce, secret.as_bytes())
+            .map_err(|_| "couldn't encrypt the password")?;
+        let mut blob = nonce.to_vec();
+        blob.extend(ciphertext);
+
+        let mut secrets = self.read_file().unwrap_or_default();
+        secrets.insert(account.into(), STANDARD.encode(blob));
+        write_private(&self.file, serde_json::to_string(&secrets)?.as_bytes())
+    }
+    pub fn delete(&self, account: &str) -> Result<(), Box<dyn Error>> {
+        if self.use_keyring {
+            // it's fine if it was never there, or if there is no keyring at all
+            if let Ok(entry) = keyring::Entry::new(SERVICE, account) {
+                let _ = entry.delete_credential();
+            }
+        }
+        self.remove_from_file(account)
+    }
+
+    fn read_file(&self) -> Result<HashMap<String, String>, Box<dyn Error>> {
+        Ok(serde_json::from_str(&fs::read_to_string(&self.file)?)?)
+    }
+    fn remove_from_file(&self, account: &str) -> Result<(), Box<dyn Error>> {
+        let Ok(mut secrets) = self.read_file() else {
+            return Ok(());
+        };
+        if secrets.remove(account).is_some() {
+            write_private(&self.file, serde_json::to_string(&secrets)?.as_bytes())?;
+        }
+        Ok(())
+    }
+    // the key is made on first use and only readable by the user
+    fn cipher(&self) -> Result<ChaCha20Poly1305, Box<dyn Error>> {
+        if let Ok(key) = fs::read(&self.key_file) {
+            if key.len() == 32 {
+                return Ok(ChaCha20Poly1305::new(Key::from_slice(&key)));
+            }
+        }
+        let key = ChaCha20Poly1305::generate_key(&mut OsRng);
+        write_private(&self.key_file, &key)?;
+        // anything encrypted with the old key is unreadable now
+        let _ = fs::remove_file(&self.file);
+        Ok(ChaCha20Poly1305::new(&key))
+    }
+}
+
+/// encrypts with a key stretched out of the passphrase
+pub fn seal(passphrase: &str, secret: &str) -> Result<String, Box<dyn Error>> {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = passphrase_cipher(passphrase, &salt)?
+        .encrypt(&nonce, secret.as_bytes())
+        .map_err(|_| "couldn't encrypt the password")?;
+    let mut blob = salt.to_vec();
+    blob.extend(nonce);
+    blob.extend(ciphertext);
+    Ok(format!("{}{}", SEALED_PREFIX, STANDARD.encode(blob)))
+}
+/// None if the passphrase is wrong or the blob is mangled
+pub fn unseal(passphrase: &str, sealed: &str) -> Option<String> {
+    let blob = STANDARD.decode(sealed.strip_prefix(SEALED_PREFIX)?).ok()?;
+    if blob.len() < SALT_LEN + NONCE_LEN {
+        return None;
+    }
+    let (salt, rest) = blob.split_at(SALT_LEN);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+    let plaintext = passphrase_cipher(passphrase, salt)
+        .ok()?
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .ok()?;
+    String::from_utf8(plaintext).ok()
+}
+/// older versions stored the bare password
+pub fn is_sealed(secret: &str) -> bool {
+    secret.starts_with(SEALED_PREFIX)
+}
+fn passphrase_cipher(passphrase: &str, salt: &[u8]) -> Result<ChaCha20Poly1305, Box<dyn Error>> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| e.to_string())?;
+    Ok(ChaCha20Poly1305::new(Key::from_slice(&key)))
+}
+
+fn write_private(path: &Path, contents: &[u8]) -> Result<(), Box<dyn Error>> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    std::io::Write::write_all(&mut options.open(path)?, contents)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a fresh directory per test, so they can run side by side
+    fn file_store(name: &str) -> SecretStore {
+        let dir =
+            std::env::temp_dir().join(format!("schoolterm-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        SecretStore {
+            file: dir.join("schoolterm-secrets"),
+            key_file: dir.join("schoolterm-key"),
+            use_keyring: false,
+        }
+    }
+
+    #[test]
+    fn seal_round_trip() {
+        let sealed = seal("correct horse", "hash").unwrap();
+        assert!(is_sealed(&sealed));
+        assert_eq!(unseal("correct horse", &sealed).as_deref(), Some("hash"));
+    }
+
+    #[test]
+    fn unseal_wrong_passphrase() {
+        let sealed = seal("correct horse", "hash").unwrap();
+        assert_eq!(unseal("battery staple", &sealed), None);
+        assert_eq!(unseal("correct horse", "hash"), None);
+    }
+
+    #[test]
+    fn file_store_set_get_delete() {
+        let store = file_store("set-get-delete");
+        let account = SecretStore::account("user", "https://example.com");
+        assert_eq!(store.get(&account), None);
+
+        store.set(&account, "first").unwrap();
+        store.set(&account, "second").unwrap();
+        store.set("someone@else", "other").unwrap();
+        assert_eq!(store.get(&account).as_deref(), Some("second"));
+        // it's encrypted on disk
+        assert!(!fs::read_to_string(&store.file).unwrap().contains("second"));
+
+        store.delete(&account).unwrap();
+        assert_eq!(store.get(&account), None);
+        assert_eq!(store.get("someone@else").as_deref(), Some("other"));
+        // deleting what isn't there is fine
+        store.delete(&account).unwrap();
+
+        fs::remove_dir_all(store.file.parent().unwrap()).unwrap();
+    }
+}
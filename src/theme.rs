@@ -0,0 +1,185 @@
+// the built in themes, plus any from ~/.config/schoolterm-themes.toml, which look like
+//
+// [mine]
+// bg = "#0a0a0a"
+// bg_accent = "black"
+// fg = [0, 0, 139]
+// fg_accent = 12
+// font = "white"
+//
+// colors are "#rrggbb", [r, g, b], an ansi color name or a 256 color number.
+// a theme with the same name as a built in one replaces it
+use console_engine::Color;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::tui::Theme;
+
+pub struct Themes {
+    list: Vec<Theme>,
+    current: usize,
+}
+impl Themes {
+    pub fn load(file: &Path, selected: Option<&str>) -> Result<Self, String> {
+        let mut list = builtin();
+        if let Ok(contents) = fs::read_to_string(file) {
+            let specs: BTreeMap<String, ThemeSpec> = toml::from_str(&contents)
+                .map_err(|e| format!("couldn't read {}: {}", file.display(), e))?;
+            for (name, spec) in specs {
+                let theme = spec
+                    .into_theme(&name)
+                    .map_err(|e| format!("theme {} in {}: {}", name, file.display(), e))?;
+                match list.iter_mut().find(|t| t.name == name) {
+                    Some(t) => *t = theme,
+                    None => list.push(theme),
+                }
+            }
+        }
+        let current = selected
+            .and_then(|name| list.iter().position(|t| t.name == name))
+            .unwrap_or_default();
+        Ok(Themes { list, current })
+    }
+    pub fn current(&self) -> &Theme {
+        &self.list[self.current]
+    }
+    pub fn next(&mut self) -> &Theme {
+        self.current = (self.current + 1) % self.list.len();
+        self.current()
+    }
+}
+
+fn builtin() -> Vec<Theme> {
+    vec![
+        Theme::default(),
+        Theme {
+            name: "light".into(),
+            bg: Color::Rgb {
+                r: 245,
+                g: 245,
+                b: 245,
+            },
+            bg_accent: Color::Rgb {
+                r: 220,
+                g: 220,
+                b: 225,
+            },
+            fg: Color::DarkBlue,
+            fg_accent: Color::Blue,
+            font: Color::Black,
+        },
+        Theme {
+            name: "high-contrast".into(),
+            bg: Color::Black,
+            bg_accent: Color::Black,
+            fg: Color::White,
+            fg_accent: Color::Yellow,
+            font: Color::White,
+        },
+        Theme {
+            name: "solarized".into(),
+            bg: Color::Rgb { r: 0, g: 43, b: 54 },
+            bg_accent: Color::Rgb { r: 7, g: 54, b: 66 },
+            fg: Color::Rgb {
+                r: 88,
+                g: 110,
+                b: 117,
+            },
+            fg_accent: Color::Rgb {
+                r: 38,
+                g: 139,
+                b: 210,
+            },
+            font: Color::Rgb {
+                r: 147,
+                g: 161,
+                b: 161,
+            },
+        },
+    ]
+}
+
+#[derive(Deserialize)]
+struct ThemeSpec {
+    bg: ColorSpec,
+    bg_accent: ColorSpec,
+    fg: ColorSpec,
+    fg_accent: ColorSpec,
+    font: ColorSpec,
+}
+impl ThemeSpec {
+    fn into_theme(self, name: &str) -> Result<Theme, String> {
+        Ok(Theme {
+            name: name.into(),
+            bg: self.bg.color()?,
+            bg_accent: self.bg_accent.color()?,
+            fg: self.fg.color()?,
+            fg_accent: self.fg_accent.color()?,
+            font: self.font.color()?,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ColorSpec {
+    Rgb([u8; 3]),
+    Ansi(u8),
+    Named(String),
+}
+impl ColorSpec {
+    fn color(&self) -> Result<Color, String> {
+        let name = match self {
+            ColorSpec::Rgb([r, g, b]) => {
+                return Ok(Color::Rgb {
+                    r: *r,
+                    g: *g,
+                    b: *b,
+                })
+            }
+            ColorSpec::Ansi(n) => return Ok(Color::AnsiValue(*n)),
+            ColorSpec::Named(name) => name,
+        };
+        if let Some(hex) = name.strip_prefix('#') {
+            let channel = |i: usize| {
+                hex.get(i..i + 2)
+                    .and_then(|c| u8::from_str_radix(c, 16).ok())
+                    .ok_or_else(|| format!("{} isn't a #rrggbb color", name))
+            };
+            if hex.len() != 6 {
+                return Err(format!("{} isn't a #rrggbb color", name));
+            }
+            return Ok(Color::Rgb {
+                r: channel(0)?,
+                g: channel(2)?,
+                b: channel(4)?,
+            });
+        }
+        let normalized: String = name
+            .chars()
+            .filter(|c| !matches!(c, '_' | '-' | ' '))
+            .collect::<String>()
+            .to_lowercase();
+        Ok(match normalized.as_str() {
+            "black" => Color::Black,
+            "darkgrey" | "darkgray" => Color::DarkGrey,
+            "red" => Color::Red,
+            "darkred" => Color::DarkRed,
+            "green" => Color::Green,
+            "darkgreen" => Color::DarkGreen,
+            "yellow" => Color::Yellow,
+            "darkyellow" => Color::DarkYellow,
+            "blue" => Color::Blue,
+            "darkblue" => Color::DarkBlue,
+            "magenta" => Color::Magenta,
+            "darkmagenta" => Color::DarkMagenta,
+            "cyan" => Color::Cyan,
+            "darkcyan" => Color::DarkCyan,
+            "white" => Color::White,
+            "grey" | "gray" => Color::Grey,
+            _ => return Err(format!("unknown color {}", name)),
+        })
+    }
+}
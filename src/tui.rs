@@ -1,5 +1,7 @@
+use std::cmp::Ordering;
 use std::error::Error;
 
+use chrono::NaiveDate;
 use console_engine::crossterm::event::{self, MouseEvent, MouseEventKind};
 use console_engine::forms::FormField;
 use console_engine::{
@@ -12,52 +14,59 @@ use console_engine::{
     Color, ConsoleEngine, KeyCode, KeyModifiers,
 };
 use termsize::Size;
+use tokio::sync::oneshot::error::TryRecvError;
 use tokio::sync::oneshot::Receiver;
 
+use crate::keymap::{Action, Keymap};
 use crate::UserData;
 
 pub struct Tui {
     pub size: Size,
     pub engine: ConsoleEngine,
+    pub theme: Theme,
+    pub keymap: Keymap,
 }
 impl Tui {
-    pub fn new() -> Result<Self, Box<dyn Error>> {
+    pub fn new(theme: Theme, keymap: Keymap) -> Result<Self, Box<dyn Error>> {
         let size = termsize::get().unwrap();
         let engine = ConsoleEngine::init(size.cols.into(), size.rows.into(), 20).unwrap();
 
-        Ok(Tui { size, engine })
+        Ok(Tui {
+            size,
+            engine,
+            theme,
+            keymap,
+        })
     }
 
-    pub fn poll_blocking<T>(&mut self, mut resp_rx: Receiver<T>) -> Result<T, Box<dyn Error>> {
+    /// shows a loading screen until the response comes in. None if the user gave up on it
+    pub fn poll_blocking<T>(
+        &mut self,
+        mut resp_rx: Receiver<T>,
+    ) -> Result<Option<T>, Box<dyn Error>> {
         let mut counter = 0;
         let mut dots = 0;
         loop {
             match self.engine.poll() {
                 Event::Frame => {
                     match resp_rx.try_recv() {
-                        Ok(student) => {
-                            return Ok(student);
+                        Ok(resp) => return Ok(Some(resp)),
+                        Err(TryRecvError::Closed) => {
+                            return Err("the network thread went away".into())
                         }
-                        _ => (),
+                        Err(TryRecvError::Empty) => (),
                     }
                     counter += 1;
                     if counter % 15 == 1 {
                         self.engine.clear_screen();
-                        self.engine.fill(pixel::pxl_bg(
-                            ' ',
-                            Color::Rgb {
-                                r: 10,
-                                g: 10,
-                                b: 10,
-                            },
-                        ));
+                        self.engine.fill(pixel::pxl_bg(' ', self.theme.bg));
 
                         self.engine.print_fbg(
                             self.size.cols as i32 / 2,
                             self.size.rows as i32 / 2,
                             &format!("Loading{}", ".".repeat(dots)),
-                            Color::Black,
-                            Color::Grey,
+                            self.theme.bg,
+                            self.theme.font,
                         );
                         self.engine.draw();
 
@@ -71,19 +80,20 @@ impl Tui {
                     self.engine.resize(x.into(), y.into());
                     self.size = Size { rows: y, cols: x }
                 }
+                Event::Key(key) => match self.keymap.action(&key) {
+                    Some(Action::Quit) => return Err(Box::new(crate::Exit {})),
+                    Some(Action::Back) => return Ok(None),
+                    _ => (),
+                },
                 _ => (),
             }
         }
     }
 
-    /// eventually we move this form into a widget, and then move tui_thread into here
-    pub fn userdata_form(&mut self, userdata: &mut UserData) {
-        let engine = &mut self.engine;
-
-        let theme = FormStyle {
-            border: Some(BorderStyle::new_light().with_colors(Color::DarkBlue, Color::Black)),
-            ..Default::default()
-        };
+    /// eventually we move this form into a widget, and then move tui_thread into here.
+    /// false if the user backed out of it
+    pub fn userdata_form(&mut self, userdata: &mut UserData, error: Option<&str>) -> bool {
+        let theme = self.form_style();
         // Create a new Form
         let mut form = Form::new(
             (self.size.cols / 2).into(),
@@ -118,6 +128,57 @@ impl Tui {
                 ..Default::default()
             },
         );
+        if !self.run_form(&mut form, error) {
+            return false;
+        }
+        userdata.baseurl = form_string(&form, "baseurl");
+        userdata.username = form_string(&form, "username");
+        userdata.password = form_string(&form, "password");
+        true
+    }
+    /// a single field form, None if the user backed out of it
+    pub fn prompt_form(
+        &mut self,
+        title: &'static str,
+        label: &'static str,
+        error: Option<&str>,
+        hidden: bool,
+    ) -> Option<String> {
+        let theme = self.form_style();
+        let mut form = Form::new(
+            (self.size.cols / 2).into(),
+            4,
+            FormOptions {
+                style: theme,
+                label: Some(title),
+                ..Default::default()
+            },
+        );
+        let options = FormOptions {
+            style: theme,
+            label: Some(label),
+            ..Default::default()
+        };
+        if hidden {
+            form.build_field::<HiddenText>("value", options);
+        } else {
+            form.build_field::<Text>("value", options);
+        }
+        self.run_form(&mut form, error)
+            .then(|| form_string(&form, "value"))
+    }
+    fn form_style(&self) -> FormStyle {
+        FormStyle {
+            border: Some(BorderStyle::new_light().with_colors(self.theme.fg, self.theme.bg_accent)),
+            fg: self.theme.font,
+            bg: self.theme.bg,
+        }
+    }
+    /// shows the form with `error` under it until it's filled in. false if escape or ctrl+c was
+    /// pressed, which back out so whoever asked can still save the config
+    fn run_form(&mut self, form: &mut Form, error: Option<&str>) -> bool {
+        let colors = self.theme.clone();
+        let engine = &mut self.engine;
         form.set_active(true);
 
         while !form.is_finished() {
@@ -127,69 +188,128 @@ impl Tui {
                 Event::Frame => {
                     engine.clear_screen();
 
-                    engine.fill(pixel::pxl_bg(
-                        ' ',
-                        Color::Rgb {
-                            r: 10,
-                            g: 10,
-                            b: 10,
-                        },
-                    ));
-                    engine.print_screen(
-                        (engine.get_width() / 4) as i32,
-                        (engine.get_height() / 4) as i32,
-                        form.draw((engine.frame_count % 8 > 3) as usize),
-                    );
+                    engine.fill(pixel::pxl_bg(' ', colors.bg));
+                    let form_screen = form.draw((engine.frame_count % 8 > 3) as usize);
+                    let form_y = (engine.get_height() / 4) as i32;
+                    engine.print_screen((engine.get_width() / 4) as i32, form_y, form_screen);
+                    if let Some(error) = error {
+                        engine.print_fbg(
+                            (engine.get_width() / 4) as i32,
+                            form_y + form_screen.get_height() as i32 + 1,
+                            error,
+                            Color::Red,
+                            colors.bg,
+                        );
+                    }
                     engine.draw();
                 }
                 Event::Resize(x, y) => {
                     engine.resize(x.into(), y.into());
                 }
-
-                // exit with Escape
                 Event::Key(KeyEvent {
                     code: KeyCode::Esc,
                     modifiers: _,
-                }) => {
-                    break;
-                }
-
-                // exit with CTRL+C
-                Event::Key(KeyEvent {
+                })
+                | Event::Key(KeyEvent {
                     code: KeyCode::Char('c'),
                     modifiers: KeyModifiers::CONTROL,
                 }) => {
-                    panic!();
-                    break;
+                    return false;
                 }
                 // Let the form handle the unhandled events
                 event => form.handle_event(event),
             }
         }
-        userdata.baseurl = match form.get_field_output("baseurl").unwrap() {
-            console_engine::forms::FormValue::String(s) => s,
-            _ => unreachable!(),
-        };
-        userdata.username = match form.get_field_output("username").unwrap() {
-            console_engine::forms::FormValue::String(s) => s,
-            _ => unreachable!(),
-        };
+        true
+    }
+    /// a list to pick from with the arrows and enter, None if escape was pressed
+    pub fn choose(&mut self, title: &str, options: &[String]) -> Option<usize> {
+        let mut index = 0;
+        loop {
+            match self.engine.poll() {
+                Event::Frame => {
+                    let bg = self.theme.bg;
+                    let w = (self.size.cols / 2) as i32;
+                    let x = (self.size.cols / 4) as i32;
+                    let y = (self.size.rows / 4) as i32;
+                    self.engine.clear_screen();
+                    self.engine.fill(pixel::pxl_bg(' ', bg));
+                    self.engine.rect_border(
+                        x,
+                        y,
+                        x + w,
+                        y + options.len() as i32 + 1,
+                        BorderStyle::new_light().with_colors(self.theme.fg, self.theme.bg_accent),
+                    );
+                    self.engine.print_fbg(
+                        x + 2,
+                        y,
+                        &format!("┤{}├", title),
+                        self.theme.fg,
+                        self.theme.bg_accent,
+                    );
+                    for (i, option) in options.iter().enumerate() {
+                        let (fg, bg) = if i == index {
+                            (self.theme.bg, self.theme.fg_accent)
+                        } else {
+                            (self.theme.font, bg)
+                        };
+                        self.engine
+                            .print_fbg(x + 2, y + 1 + i as i32, option, fg, bg);
+                    }
+                    self.engine.draw();
+                }
+                Event::Resize(x, y) => {
+                    self.engine.resize(x.into(), y.into());
+                    self.size = Size { rows: y, cols: x }
+                }
+                Event::Key(key) => match self.keymap.action(&key) {
+                    Some(Action::Up) => index = index.saturating_sub(1),
+                    Some(Action::Down) if index + 1 < options.len() => index += 1,
+                    Some(Action::Home) => index = 0,
+                    Some(Action::End) => index = options.len().saturating_sub(1),
+                    Some(Action::Select) if !options.is_empty() => return Some(index),
+                    Some(Action::Back | Action::Quit) => return None,
+                    _ => (),
+                },
+                _ => (),
+            }
+        }
+    }
+}
 
-        userdata.password = match form.get_field_output("password").unwrap() {
-            console_engine::forms::FormValue::String(s) => s,
-            _ => unreachable!(),
-        };
+fn form_string(form: &Form, field: &str) -> String {
+    match form.get_field_output(field).unwrap() {
+        console_engine::forms::FormValue::String(s) => s,
+        _ => unreachable!(),
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct Theme {
+    pub name: String,
     pub bg: Color,
     pub bg_accent: Color,
     pub fg: Color,
     pub fg_accent: Color,
     pub font: Color,
 }
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            name: "dark".into(),
+            bg: Color::Rgb {
+                r: 10,
+                g: 10,
+                b: 10,
+            },
+            bg_accent: Color::Black,
+            fg: Color::DarkBlue,
+            fg_accent: Color::Blue,
+            font: Color::White,
+        }
+    }
+}
 
 #[derive(Debug, Clone, Default)]
 pub struct Rect {
@@ -203,6 +323,10 @@ pub trait Widget: AsWidget {
     fn feed_event(&mut self, event: event::Event) -> Option<event::Event>;
     fn draw(&mut self, theme: &Theme, selected: bool) -> Screen;
     fn rect(&self) -> &Rect;
+    /// widgets that take typing get the raw keys before the keymap does
+    fn wants_text(&self) -> bool {
+        false
+    }
 }
 pub trait AsWidget {
     fn as_widget(&mut self) -> &mut dyn Widget;
@@ -249,16 +373,122 @@ pub struct Table {
     pub indecies: Vec<String>,
     pub data: Vec<Vec<String>>,
     pub index: usize,
+    /// the first row on screen, it follows `index` around
+    pub scroll: usize,
+    /// the column rows are sorted by and whether it's descending. None shows them as they came
+    pub sort: Option<(usize, bool)>,
+    /// only rows matching this are shown, see `find_match`
+    pub filter: String,
+    pub clicked: bool,
     pub rect: Rect,
 }
+impl Table {
+    /// how many rows fit under the header
+    fn page(&self) -> usize {
+        self.rect.h.saturating_sub(4).max(1) as usize
+    }
+    /// indexes into `data`, in the order they're shown
+    pub fn order(&self) -> Vec<usize> {
+        let terms: Vec<&str> = self.filter.split_whitespace().collect();
+        // every word has to show up in some column
+        let mut order: Vec<usize> = (0..self.data.len())
+            .filter(|i| {
+                terms.iter().all(|term| {
+                    self.data[*i]
+                        .iter()
+                        .any(|cell| find_match(cell, term).is_some())
+                })
+            })
+            .collect();
+        let Some((col, descending)) = self.sort.filter(|(c, _)| *c < self.indecies.len()) else {
+            return order;
+        };
+        let keys: Vec<SortKey> = self
+            .data
+            .iter()
+            .map(|row| SortKey::new(row.get(col).map(|s| s.as_str()).unwrap_or_default()))
+            .collect();
+        order.sort_by(|a, b| {
+            let (a, b) = (&keys[*a], &keys[*b]);
+            // blanks stay at the bottom either way
+            match (a, b) {
+                (SortKey::Empty, SortKey::Empty) => Ordering::Equal,
+                (SortKey::Empty, _) => Ordering::Greater,
+                (_, SortKey::Empty) => Ordering::Less,
+                _ if descending => b.partial_cmp(a).unwrap_or(Ordering::Equal),
+                _ => a.partial_cmp(b).unwrap_or(Ordering::Equal),
+            }
+        });
+        order
+    }
+    /// the selected row as an index into `data`
+    pub fn selected(&self) -> Option<usize> {
+        self.order().get(self.index).copied()
+    }
+    /// sorts by the next column over, and back to unsorted after the last one
+    pub fn next_sort(&mut self) {
+        self.resort(match self.sort {
+            None if !self.indecies.is_empty() => Some((0, false)),
+            Some((col, _)) if col + 1 < self.indecies.len() => Some((col + 1, false)),
+            _ => None,
+        });
+    }
+    pub fn reverse_sort(&mut self) {
+        self.resort(self.sort.map(|(col, descending)| (col, !descending)));
+    }
+    // the same row stays selected
+    fn resort(&mut self, sort: Option<(usize, bool)>) {
+        let row = self.selected();
+        self.sort = sort;
+        if let Some(row) = row {
+            self.index = self.order().iter().position(|r| *r == row).unwrap_or(0);
+        }
+    }
+}
+
+/// what a cell gets compared as. dates are the mm/dd/yy from `parse_datestr`, scores like 9/10 are
+/// compared by their ratio
+#[derive(PartialEq, PartialOrd)]
+enum SortKey {
+    Number(f64),
+    Date(NaiveDate),
+    Text(String),
+    Empty,
+}
+impl SortKey {
+    fn new(cell: &str) -> Self {
+        let cell = cell.trim();
+        if cell.is_empty() || cell == "/" {
+            return SortKey::Empty;
+        }
+        let number = |s: &str| s.trim().parse::<f64>().ok().filter(|n| n.is_finite());
+        if let Some(n) = number(cell.strip_suffix('%').unwrap_or(cell)) {
+            return SortKey::Number(n);
+        }
+        if let Ok(date) = NaiveDate::parse_from_str(cell, "%m/%d/%y") {
+            return SortKey::Date(date);
+        }
+        if let Some((score, max)) = cell.split_once('/') {
+            match (number(score), number(max)) {
+                (Some(score), Some(max)) if max > 0.0 => return SortKey::Number(score / max),
+                // not graded yet
+                (None, Some(_)) if score.trim().is_empty() => return SortKey::Empty,
+                _ => (),
+            }
+        }
+        SortKey::Text(cell.to_lowercase())
+    }
+}
 impl Widget for Table {
     fn feed_event(&mut self, event: event::Event) -> Option<event::Event> {
+        let last = self.order().len().saturating_sub(1);
+        self.index = self.index.min(last);
         match event {
             event::Event::Key(KeyEvent {
                 code: KeyCode::Down,
                 modifiers: KeyModifiers::NONE,
             }) => {
-                if self.index >= self.data.len() - 1 {
+                if self.index >= last {
                     return Some(event);
                 }
                 self.index += 1;
@@ -274,6 +504,38 @@ impl Widget for Table {
                 self.index -= 1;
             }
 
+            event::Event::Key(KeyEvent {
+                code: KeyCode::PageDown,
+                modifiers: _,
+            }) => {
+                self.index = (self.index + self.page()).min(last);
+            }
+            event::Event::Key(KeyEvent {
+                code: KeyCode::PageUp,
+                modifiers: _,
+            }) => {
+                self.index = self.index.saturating_sub(self.page());
+            }
+            event::Event::Key(KeyEvent {
+                code: KeyCode::Home,
+                modifiers: _,
+            }) => {
+                self.index = 0;
+            }
+            event::Event::Key(KeyEvent {
+                code: KeyCode::End,
+                modifiers: _,
+            }) => {
+                self.index = last;
+            }
+
+            event::Event::Key(KeyEvent {
+                code: KeyCode::Enter,
+                modifiers: KeyModifiers::NONE,
+            }) if !self.data.is_empty() => {
+                self.clicked = true;
+            }
+
             _ => return Some(event),
         }
         None
@@ -290,7 +552,7 @@ impl Widget for Table {
             self.rect.h as i32 - 1,
             BorderStyle::new_light().with_colors(borderfg, theme.bg_accent),
         );
-        if self.data.len() <= 0 || self.indecies.len() <= 0 {
+        if self.data.is_empty() || self.indecies.is_empty() {
             return screen;
         }
 
@@ -316,7 +578,7 @@ impl Widget for Table {
 
         for coln in 0..averages.len() {
             averages[coln] = (averages[coln] * multiplier)
-                .max(self.indecies[coln].len() + 2)
+                .max(self.indecies[coln].len() + 4)
                 .min(maxes[coln] + 1);
         }
 
@@ -336,7 +598,12 @@ impl Widget for Table {
         screen.print_fbg(self.rect.w as i32 - 1, 2, "╡", theme.fg, theme.bg_accent);
         let mut x = 1;
         for (i, s) in self.indecies.iter().enumerate() {
-            screen.print_fbg(x as i32 + 1, 1, &s, theme.font, theme.bg);
+            let header = match self.sort {
+                Some((col, false)) if col == i => format!("{} ▲", s),
+                Some((col, true)) if col == i => format!("{} ▼", s),
+                _ => s.clone(),
+            };
+            screen.print_fbg(x as i32 + 1, 1, &header, theme.font, theme.bg);
             if i != 0 {
                 screen.v_line(
                     x as i32 - 1,
@@ -358,16 +625,73 @@ impl Widget for Table {
             }
             x += lens[i];
         }
-        let mut y = 3;
-        for (_i, row) in self.data.iter().enumerate() {
+        // keep the selected row on screen
+        let page = self.page();
+        let order = self.order();
+        self.index = self.index.min(order.len().saturating_sub(1));
+        if self.index < self.scroll {
+            self.scroll = self.index;
+        } else if self.index >= self.scroll + page {
+            self.scroll = self.index + 1 - page;
+        }
+        self.scroll = self.scroll.min(order.len().saturating_sub(page));
+
+        let terms: Vec<&str> = self.filter.split_whitespace().collect();
+        for (y, (i, row)) in (3..).zip(order.iter().enumerate().skip(self.scroll).take(page)) {
+            let row = &self.data[*row];
+            let (fg, bg) = if i != self.index {
+                (theme.font, theme.bg)
+            } else if selected {
+                (theme.bg, theme.fg_accent)
+            } else {
+                (theme.font, theme.bg_accent)
+            };
             let mut x = 1;
             for (j, s) in row.iter().enumerate() {
-                let mut str = s.clone();
-                str.truncate(lens[j] as usize - 2);
-                screen.print_fbg(x as i32 + 1, y, &str, theme.font, theme.bg);
+                if i == self.index {
+                    // stop short of the column lines
+                    screen.h_line(
+                        x as i32,
+                        y,
+                        (x + lens[j]) as i32 - 2,
+                        pixel::pxl_bg(' ', bg),
+                    );
+                }
+                let text = truncate(s, lens[j].saturating_sub(2) as usize);
+                screen.print_fbg(x as i32 + 1, y, text, fg, bg);
+                // redraw the matched characters picked out
+                for term in &terms {
+                    for pos in find_match(s, term).unwrap_or_default() {
+                        if let Some(c) = text.chars().nth(pos) {
+                            screen.set_pxl(
+                                x as i32 + 1 + pos as i32,
+                                y,
+                                pixel::pxl_fbg(c, theme.fg_accent, theme.bg_accent),
+                            );
+                        }
+                    }
+                }
                 x += lens[j];
             }
-            y += 1;
+        }
+
+        // scrollbar along the right border, only when there's more than fits
+        if order.len() > page {
+            let thumb = (page * page / order.len()).max(1);
+            let max_scroll = order.len() - page;
+            let top = self.scroll * (page - thumb) / max_scroll;
+            for y in 0..page {
+                let c = if y >= top && y < top + thumb {
+                    '┃'
+                } else {
+                    '│'
+                };
+                screen.set_pxl(
+                    self.rect.w as i32 - 1,
+                    3 + y as i32,
+                    pixel::pxl_fbg(c, theme.fg_accent, theme.bg_accent),
+                );
+            }
         }
 
         screen
@@ -386,7 +710,7 @@ pub struct Drawer {
 }
 impl Widget for Drawer {
     fn feed_event(&mut self, event: event::Event) -> Option<event::Event> {
-        if self.buttons.is_empty(){
+        if self.buttons.is_empty() {
             return Some(event);
         }
         match event {
@@ -448,7 +772,7 @@ impl Widget for Drawer {
             ),
         );
 
-        let total_text_space = if self.buttons.len() > 0 {
+        let total_text_space = if !self.buttons.is_empty() {
             self.buttons
                 .iter()
                 .map(|f| f.len() as u32)
@@ -458,10 +782,7 @@ impl Widget for Drawer {
             0
         };
 
-        let mut do_scrolling: bool = false;
-
         let x_spacing = if total_text_space + 2 >= self.rect.w {
-            do_scrolling = true;
             2
         } else {
             (self.rect.w - total_text_space) / (self.buttons.len().max(1) as u32 + 1)
@@ -471,13 +792,11 @@ impl Widget for Drawer {
 
         let mut x = x_spacing;
         for (i, b) in self.buttons.iter_mut().enumerate() {
-            let mut str = b.clone();
-
-            str.truncate(self.rect.w as usize - x as usize - 2);
+            let str = truncate(b, (self.rect.w as usize).saturating_sub(x as usize + 2));
             profile_screen.print_fbg(
                 x as i32,
                 y_spacing,
-                &str,
+                str,
                 if self.clicked_index == i {
                     theme.fg_accent
                 } else if self.index == i && selected {
@@ -510,3 +829,356 @@ impl Widget for Drawer {
         &self.rect
     }
 }
+
+/// a scrollable block of wrapped text
+pub struct TextBox {
+    pub title: String,
+    pub lines: Vec<String>,
+    /// first visible line, clamped while drawing so usize::MAX sticks to the bottom
+    pub scroll: usize,
+    pub rect: Rect,
+}
+impl TextBox {
+    fn wrapped(&self) -> Vec<String> {
+        let width = self.rect.w.saturating_sub(4).max(1) as usize;
+        self.lines.iter().flat_map(|l| wrap(l, width)).collect()
+    }
+    fn page(&self) -> usize {
+        self.rect.h.saturating_sub(2).max(1) as usize
+    }
+}
+impl Widget for TextBox {
+    fn feed_event(&mut self, event: event::Event) -> Option<event::Event> {
+        let max_scroll = self.wrapped().len().saturating_sub(self.page());
+        self.scroll = self.scroll.min(max_scroll);
+        match event {
+            event::Event::Key(KeyEvent {
+                code: KeyCode::Down,
+                modifiers: KeyModifiers::NONE,
+            }) => {
+                if self.scroll >= max_scroll {
+                    return Some(event);
+                }
+                self.scroll += 1;
+            }
+            event::Event::Key(KeyEvent {
+                code: KeyCode::Up,
+                modifiers: KeyModifiers::NONE,
+            }) => {
+                if self.scroll < 1 {
+                    return Some(event);
+                }
+                self.scroll -= 1;
+            }
+            event::Event::Key(KeyEvent {
+                code: KeyCode::PageDown,
+                modifiers: _,
+            }) => {
+                self.scroll = (self.scroll + self.page()).min(max_scroll);
+            }
+            event::Event::Key(KeyEvent {
+                code: KeyCode::PageUp,
+                modifiers: _,
+            }) => {
+                self.scroll = self.scroll.saturating_sub(self.page());
+            }
+            _ => return Some(event),
+        }
+        None
+    }
+    fn draw(&mut self, theme: &Theme, selected: bool) -> Screen {
+        let mut screen = Screen::new(self.rect.w, self.rect.h);
+        let borderfg = if selected { theme.fg_accent } else { theme.fg };
+
+        screen.fill(pixel::pxl_bg(' ', theme.bg));
+        screen.rect_border(
+            0,
+            0,
+            self.rect.w as i32 - 1,
+            self.rect.h as i32 - 1,
+            BorderStyle::new_light().with_colors(borderfg, theme.bg_accent),
+        );
+        if !self.title.is_empty() {
+            screen.print_fbg(
+                2,
+                0,
+                &format!("┤{}├", self.title),
+                borderfg,
+                theme.bg_accent,
+            );
+        }
+
+        let lines = self.wrapped();
+        self.scroll = self.scroll.min(lines.len().saturating_sub(self.page()));
+        for (y, line) in (1..).zip(lines.iter().skip(self.scroll).take(self.page())) {
+            screen.print_fbg(2, y, line, theme.font, theme.bg);
+        }
+        if self.scroll + self.page() < lines.len() {
+            screen.print_fbg(
+                self.rect.w as i32 - 2,
+                self.rect.h as i32 - 2,
+                "↓",
+                theme.fg_accent,
+                theme.bg,
+            );
+        }
+        if self.scroll > 0 {
+            screen.print_fbg(self.rect.w as i32 - 2, 1, "↑", theme.fg_accent, theme.bg);
+        }
+        screen
+    }
+    fn rect(&self) -> &Rect {
+        &self.rect
+    }
+}
+
+/// single line text entry. enter sets `submitted`
+pub struct Input {
+    pub label: String,
+    pub value: String,
+    /// cursor position in chars
+    pub cursor: usize,
+    pub submitted: bool,
+    pub rect: Rect,
+}
+impl Input {
+    pub fn new(label: &str) -> Self {
+        Input {
+            label: label.into(),
+            value: String::new(),
+            cursor: 0,
+            submitted: false,
+            rect: Rect::default(),
+        }
+    }
+    pub fn clear(&mut self) {
+        self.value.clear();
+        self.cursor = 0;
+    }
+    fn byte_index(&self, cursor: usize) -> usize {
+        self.value
+            .char_indices()
+            .nth(cursor)
+            .map(|(i, _)| i)
+            .unwrap_or(self.value.len())
+    }
+}
+impl Widget for Input {
+    fn feed_event(&mut self, event: event::Event) -> Option<event::Event> {
+        let event::Event::Key(KeyEvent { code, modifiers }) = event else {
+            return Some(event);
+        };
+        if modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) {
+            return Some(event);
+        }
+        match code {
+            KeyCode::Char(c) => {
+                let i = self.byte_index(self.cursor);
+                self.value.insert(i, c);
+                self.cursor += 1;
+            }
+            KeyCode::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                let i = self.byte_index(self.cursor);
+                self.value.remove(i);
+            }
+            KeyCode::Delete if self.cursor < self.value.chars().count() => {
+                let i = self.byte_index(self.cursor);
+                self.value.remove(i);
+            }
+            KeyCode::Left if self.cursor > 0 => self.cursor -= 1,
+            KeyCode::Right if self.cursor < self.value.chars().count() => self.cursor += 1,
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.value.chars().count(),
+            KeyCode::Enter => self.submitted = true,
+            _ => return Some(event),
+        }
+        None
+    }
+    fn draw(&mut self, theme: &Theme, selected: bool) -> Screen {
+        let mut screen = Screen::new(self.rect.w, self.rect.h);
+        let borderfg = if selected { theme.fg_accent } else { theme.fg };
+
+        screen.fill(pixel::pxl_bg(' ', theme.bg));
+        screen.rect_border(
+            0,
+            0,
+            self.rect.w as i32 - 1,
+            self.rect.h as i32 - 1,
+            BorderStyle::new_light().with_colors(borderfg, theme.bg_accent),
+        );
+        screen.print_fbg(
+            2,
+            0,
+            &format!("┤{}├", self.label),
+            borderfg,
+            theme.bg_accent,
+        );
+
+        // keep the cursor in view on long input
+        let width = self.rect.w.saturating_sub(4) as usize;
+        let start = (self.cursor + 1).saturating_sub(width);
+        let visible: String = self.value.chars().skip(start).take(width).collect();
+        screen.print_fbg(2, 1, &visible, theme.font, theme.bg);
+        if selected {
+            let under = self.value.chars().nth(self.cursor).unwrap_or(' ');
+            screen.print_fbg(
+                2 + (self.cursor - start) as i32,
+                1,
+                &under.to_string(),
+                theme.bg,
+                theme.font,
+            );
+        }
+        screen
+    }
+    fn rect(&self) -> &Rect {
+        &self.rect
+    }
+    fn wants_text(&self) -> bool {
+        true
+    }
+}
+
+/// where `query` shows up in `text`, as char positions. a plain substring is tried first, then
+/// the query's characters in order with gaps allowed. case doesn't matter
+pub fn find_match(text: &str, query: &str) -> Option<Vec<usize>> {
+    let lower = |s: &str| -> Vec<char> {
+        s.chars()
+            .map(|c| c.to_lowercase().next().unwrap_or(c))
+            .collect()
+    };
+    let (text, query) = (lower(text), lower(query));
+    if query.is_empty() {
+        return Some(vec![]);
+    }
+    if let Some(start) = text.windows(query.len()).position(|w| w == query) {
+        return Some((start..start + query.len()).collect());
+    }
+    let mut positions = vec![];
+    let mut wanted = query.iter().peekable();
+    for (i, c) in text.iter().enumerate() {
+        if wanted.peek() == Some(&c) {
+            positions.push(i);
+            wanted.next();
+        }
+    }
+    wanted.peek().is_none().then_some(positions)
+}
+/// cuts to at most `width` characters, without splitting one in half like `String::truncate` can
+pub fn truncate(s: &str, width: usize) -> &str {
+    match s.char_indices().nth(width) {
+        Some((i, _)) => &s[..i],
+        None => s,
+    }
+}
+/// breaks text into lines of at most `width` chars, on spaces where possible
+pub fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = vec![];
+    for paragraph in text.split('\n') {
+        let mut line = String::new();
+        for word in paragraph.split(' ') {
+            let mut word = word.to_string();
+            while word.chars().count() > width {
+                if !line.is_empty() {
+                    lines.push(std::mem::take(&mut line));
+                }
+                let rest = word.split_off(word.char_indices().nth(width).unwrap().0);
+                lines.push(word);
+                word = rest;
+            }
+            if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > width {
+                lines.push(std::mem::take(&mut line));
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line += &word;
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(cells: &[&str]) -> Table {
+        Table {
+            indecies: vec!["Grade".into()],
+            data: cells.iter().map(|c| vec![c.to_string()]).collect(),
+            index: 0,
+            scroll: 0,
+            sort: None,
+            filter: String::new(),
+            clicked: false,
+            rect: Rect::default(),
+        }
+    }
+    fn sorted(table: &mut Table, descending: bool) -> Vec<&str> {
+        table.sort = Some((0, descending));
+        table
+            .order()
+            .into_iter()
+            .map(|i| table.data[i][0].as_str())
+            .collect()
+    }
+
+    #[test]
+    fn scores_sort_by_ratio() {
+        let mut table = column(&["10/10", "9/10", "40/50"]);
+        assert_eq!(sorted(&mut table, false), ["40/50", "9/10", "10/10"]);
+        assert_eq!(sorted(&mut table, true), ["10/10", "9/10", "40/50"]);
+    }
+
+    #[test]
+    fn dates_sort_by_date() {
+        let mut table = column(&["01/05/24", "12/20/23", "02/01/23"]);
+        assert_eq!(
+            sorted(&mut table, false),
+            ["02/01/23", "12/20/23", "01/05/24"]
+        );
+    }
+
+    #[test]
+    fn percents_sort_as_numbers() {
+        let mut table = column(&["9%", "85.5%", "100%"]);
+        assert_eq!(sorted(&mut table, false), ["9%", "85.5%", "100%"]);
+    }
+
+    #[test]
+    fn blanks_stay_last() {
+        let mut table = column(&["/10", "8/10", "", "/", "10/10"]);
+        assert_eq!(&sorted(&mut table, false)[..2], ["8/10", "10/10"]);
+        assert_eq!(&sorted(&mut table, true)[..2], ["10/10", "8/10"]);
+    }
+
+    #[test]
+    fn substring_beats_fuzzy() {
+        assert_eq!(
+            find_match("Mean absolute time", "mat"),
+            Some(vec![0, 2, 11])
+        );
+        // the letters show up spread out before they show up together
+        assert_eq!(find_match("m a t Math", "mat"), Some(vec![6, 7, 8]));
+    }
+
+    #[test]
+    fn match_ignores_case() {
+        assert_eq!(find_match("ENGLISH 10", "eng"), Some(vec![0, 1, 2]));
+        assert_eq!(find_match("english 10", "ENG"), Some(vec![0, 1, 2]));
+    }
+
+    #[test]
+    fn match_positions_are_chars() {
+        assert_eq!(find_match("Français", "çais"), Some(vec![4, 5, 6, 7]));
+        assert_eq!(find_match("Físic", "fsc"), Some(vec![0, 2, 4]));
+    }
+
+    #[test]
+    fn no_match() {
+        assert_eq!(find_match("Math", "science"), None);
+        assert_eq!(find_match("Math", "htam"), None);
+    }
+}